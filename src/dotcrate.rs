@@ -5,6 +5,17 @@ mod deser;
 pub use deser::StringOrBool;
 use deser::*;
 
+mod normalize;
+
+mod read;
+pub use read::{checksum, DotCrate, ReadError};
+
+mod validate;
+pub use validate::{validate, Limits, ValidationError};
+
+mod write;
+pub use write::{Builder, WriteError};
+
 /// A `Cargo.toml` manifest from or for a `.crate` file.
 // NOTE: This doesn't use borrowing deserialization because toml_edit doesn't support it.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -24,38 +35,120 @@ where
     pub build_dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub features: Option<BTreeMap<Feature, Vec<Feature>>>,
+    /// Platform-specific dependencies, keyed by target name or `cfg()` expression.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<BTreeMap<String, Platform<Feature>>>,
+    /// Badges to display on the registry, keyed by badge type (like `maintenance`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badges: Option<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+impl<Name, Feature> NormalizedManifest<Name, Feature>
+where
+    Feature: Ord + AsRef<str>,
+{
+    /// The `[features]` table, with each value parsed.
+    pub fn feature_values(&self) -> crate::feature::FeatureMap<'_> {
+        crate::feature::parse_tables(&self.features)
+    }
 }
 
 impl<Name, Feature> NormalizedManifest<Name, Feature>
 where
     Feature: Ord,
 {
+    /// Every dependency table, with the target it is for and the kind of dependencies it holds.
+    pub(crate) fn dependency_tables(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            Option<&str>,
+            super::publish::DependencyKind,
+            &BTreeMap<String, Dependency<Feature>>,
+        ),
+    > {
+        use super::publish::DependencyKind::{Build, Dev, Normal};
+        let untargeted = [
+            (None, Normal, &self.dependencies),
+            (None, Dev, &self.dev_dependencies),
+            (None, Build, &self.build_dependencies),
+        ];
+        let targeted = self.target.iter().flatten().flat_map(|(target, p)| {
+            [
+                (Some(&**target), Normal, &p.dependencies),
+                (Some(&**target), Dev, &p.dev_dependencies),
+                (Some(&**target), Build, &p.build_dependencies),
+            ]
+        });
+        untargeted
+            .into_iter()
+            .chain(targeted)
+            .filter_map(|(target, kind, deps)| Some((target, kind, deps.as_ref()?)))
+    }
+
     pub(crate) fn take_dependencies(
         &mut self,
     ) -> impl Iterator<Item = (String, Dependency<Feature>, super::publish::DependencyKind)> {
-        self.dependencies
-            .take()
-            .unwrap_or_default()
-            .into_iter()
-            .map(|d| (d, super::publish::DependencyKind::Normal))
-            .chain(
-                self.dev_dependencies
-                    .take()
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|d| (d, super::publish::DependencyKind::Dev)),
-            )
-            .chain(
-                self.build_dependencies
-                    .take()
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|d| (d, super::publish::DependencyKind::Build)),
-            )
-            .map(|((name_in_toml, d), kind)| (name_in_toml, d, kind))
+        let targeted =
+            self.target
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|(target, p)| {
+                    with_kinds(p.dependencies, p.dev_dependencies, p.build_dependencies).map(
+                        move |(name_in_toml, mut d, kind)| {
+                            d.target = Some(target.clone());
+                            (name_in_toml, d, kind)
+                        },
+                    )
+                });
+        with_kinds(
+            self.dependencies.take(),
+            self.dev_dependencies.take(),
+            self.build_dependencies.take(),
+        )
+        .chain(targeted)
     }
 }
 
+fn with_kinds<Feature>(
+    dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+    dev_dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+    build_dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+) -> impl Iterator<Item = (String, Dependency<Feature>, super::publish::DependencyKind)> {
+    dependencies
+        .unwrap_or_default()
+        .into_iter()
+        .map(|d| (d, super::publish::DependencyKind::Normal))
+        .chain(
+            dev_dependencies
+                .unwrap_or_default()
+                .into_iter()
+                .map(|d| (d, super::publish::DependencyKind::Dev)),
+        )
+        .chain(
+            build_dependencies
+                .unwrap_or_default()
+                .into_iter()
+                .map(|d| (d, super::publish::DependencyKind::Build)),
+        )
+        .map(|((name_in_toml, d), kind)| (name_in_toml, d, kind))
+}
+
+/// The dependency tables of a `[target.<platform>]` section.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Platform<Feature> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+    #[serde(alias = "dev_dependencies")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dev_dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+    #[serde(alias = "build_dependencies")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_dependencies: Option<BTreeMap<String, Dependency<Feature>>>,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct Dependency<Feature> {
@@ -74,7 +167,10 @@ pub struct Dependency<Feature> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package: Option<String>,
 
-    /// A platform name, like `x86_64-apple-darwin`
+    /// A platform name, like `x86_64-apple-darwin`, or a `cfg()` expression.
+    ///
+    /// For dependencies listed under `[target.<platform>]`, this is filled in from the table by
+    /// [`NormalizedManifest`] conversions.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target: Option<String>,
 }
@@ -88,6 +184,7 @@ pub struct Dependency<Feature> {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct Package<Name> {
+    pub edition: Option<String>,
     pub rust_version: Option<String>,
     pub name: Name,
     #[serde(deserialize_with = "version_trim_whitespace")]
@@ -106,3 +203,14 @@ pub struct Package<Name> {
     pub license_file: Option<String>,
     pub repository: Option<String>,
 }
+
+impl<Name> Package<Name> {
+    /// Parses the `license` field as an SPDX license expression, if there is one.
+    pub fn license_expression(
+        &self,
+    ) -> Option<Result<crate::license::Expression, crate::license::ParseError>> {
+        self.license
+            .as_deref()
+            .map(crate::license::Expression::parse)
+    }
+}
@@ -0,0 +1,155 @@
+use super::NormalizedManifest;
+use serde::Serialize;
+use toml_edit::{Document, Item, Table};
+
+/// The comment `cargo package` puts at the top of the normalized `Cargo.toml`.
+const PREAMBLE: &str = "\
+# THIS FILE IS AUTOMATICALLY GENERATED BY CARGO
+#
+# When uploading crates to the registry Cargo will automatically
+# \"normalize\" Cargo.toml files for maximal compatibility
+# with all versions of Cargo and also rewrite `path` dependencies
+# to registry (e.g., crates.io) dependencies.
+#
+# If you are reading this file be aware that the original Cargo.toml
+# will likely look very different (and much more reasonable).
+# See Cargo.toml.orig for the original contents.
+";
+
+/// The order cargo writes the top-level tables in.
+const TABLES: &[&str] = &[
+    "package",
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "features",
+    "target",
+    "badges",
+];
+
+/// The order cargo writes the keys of `[package]` in.
+const PACKAGE_KEYS: &[&str] = &[
+    "edition",
+    "rust-version",
+    "name",
+    "version",
+    "authors",
+    "links",
+    "description",
+    "homepage",
+    "documentation",
+    "readme",
+    "keywords",
+    "categories",
+    "license",
+    "license-file",
+    "repository",
+];
+
+/// The order cargo writes the keys of a dependency in.
+const DEPENDENCY_KEYS: &[&str] = &[
+    "version",
+    "registry-index",
+    "features",
+    "optional",
+    "default-features",
+    "package",
+    "public",
+];
+
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Sorts the keys of `table` by their position in `order`, with unknown keys last.
+fn sort_keys(table: &mut Table, order: &[&str]) {
+    let rank = |k: &str| order.iter().position(|o| *o == k).unwrap_or(order.len());
+    table.sort_values_by(|a, _, b, _| rank(a.get()).cmp(&rank(b.get())));
+}
+
+/// Turns `{ foo = { version = "1" } }` into `[<name>.foo]` tables.
+fn dependency_table(item: Item) -> Item {
+    let deps = match item.into_table() {
+        Ok(table) => table,
+        Err(_) => unreachable!("dependencies serialize as a table"),
+    };
+    let mut table = Table::new();
+    table.set_implicit(true);
+    for (name, dep) in deps {
+        let mut dep = match dep.into_table() {
+            Ok(table) => table,
+            Err(_) => unreachable!("dependencies serialize as tables"),
+        };
+        // The target is given by the enclosing `[target.<platform>]` table instead.
+        dep.remove("target");
+        sort_keys(&mut dep, DEPENDENCY_KEYS);
+        table.insert(&name, Item::Table(dep));
+    }
+    Item::Table(table)
+}
+
+impl<Name, Feature> NormalizedManifest<Name, Feature>
+where
+    Name: Serialize,
+    Feature: Ord + Serialize,
+{
+    /// Writes this manifest as the `Cargo.toml` that `cargo package` would put in a `.crate` file.
+    ///
+    /// Like cargo, this writes the generated-file comment, orders the keys of `[package]` and of
+    /// each dependency the way cargo does, and gives each dependency its own table (like
+    /// `[dependencies.foo]` or `[target."cfg(unix)".dependencies.foo]`).
+    ///
+    /// [`Dependency::target`](super::Dependency::target) is not written; put the dependency in
+    /// [`target`](Self::target) instead.
+    pub fn to_toml(&self) -> Result<String, toml_edit::ser::Error> {
+        let mut serialized = toml_edit::ser::to_document(self)?;
+        let mut doc = Document::new();
+        for &key in TABLES {
+            let item = match serialized.remove(key) {
+                Some(item) => item,
+                None => continue,
+            };
+            let item = match key {
+                "package" | "features" => {
+                    let mut table = match item.into_table() {
+                        Ok(table) => table,
+                        Err(_) => unreachable!("{key} serializes as a table"),
+                    };
+                    if key == "package" {
+                        sort_keys(&mut table, PACKAGE_KEYS);
+                    }
+                    Item::Table(table)
+                }
+                "target" | "badges" => {
+                    let outer = match item.into_table() {
+                        Ok(table) => table,
+                        Err(_) => unreachable!("{key} serializes as a table"),
+                    };
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    for (name, inner) in outer {
+                        let inner = match inner.into_table() {
+                            Ok(table) => table,
+                            Err(_) => unreachable!("{key} entries serialize as tables"),
+                        };
+                        let inner = if key == "target" {
+                            let mut platform = Table::new();
+                            platform.set_implicit(true);
+                            for (kind, deps) in inner {
+                                if DEPENDENCY_TABLES.contains(&&*kind) {
+                                    platform.insert(&kind, dependency_table(deps));
+                                }
+                            }
+                            platform
+                        } else {
+                            inner
+                        };
+                        table.insert(&name, Item::Table(inner));
+                    }
+                    Item::Table(table)
+                }
+                _ => dependency_table(item),
+            };
+            doc.insert(key, item);
+        }
+        Ok(format!("{PREAMBLE}\n{doc}"))
+    }
+}
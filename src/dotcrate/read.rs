@@ -0,0 +1,257 @@
+use super::validate::Limited;
+use super::{Limits, NormalizedManifest};
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+/// The SHA-256 checksum of a `.crate` file, as recorded in the index.
+pub fn checksum(dotcrate: &[u8]) -> [u8; 32] {
+    Sha256::digest(dotcrate).into()
+}
+
+/// An error from reading a `.crate` file.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The file could not be read, or is not a gzip-compressed tarball.
+    Io(io::Error),
+    /// The archive decompresses to more than [`Limits::max_unpacked_size`] bytes.
+    TooLarge {
+        /// The limit that was exceeded.
+        limit: u64,
+    },
+    /// A path in the archive is not inside the archive's top-level directory.
+    OutsidePrefix(String),
+    /// The archive does not contain a `Cargo.toml` in its top-level directory.
+    MissingManifest,
+    /// The `Cargo.toml` in the archive is not valid UTF-8.
+    ManifestEncoding(std::str::Utf8Error),
+    /// The `Cargo.toml` in the archive could not be parsed.
+    Manifest(toml_edit::de::Error),
+    /// The archive's top-level directory does not match the name and version in its `Cargo.toml`.
+    PrefixMismatch {
+        /// The top-level directory of the archive.
+        prefix: String,
+        /// The `<name>-<version>` from `Cargo.toml`.
+        expected: String,
+    },
+    /// The readme named by the manifest is missing or is not valid UTF-8.
+    Readme(crate::publish::ReadmeError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::TooLarge { limit } => {
+                write!(f, "archive decompresses to more than {limit} bytes")
+            }
+            Self::OutsidePrefix(path) => {
+                write!(f, "`{path}` is outside the archive's top-level directory")
+            }
+            Self::MissingManifest => write!(f, "archive does not contain a Cargo.toml"),
+            Self::ManifestEncoding(e) => write!(f, "Cargo.toml is not valid UTF-8: {e}"),
+            Self::Manifest(e) => write!(f, "failed to parse Cargo.toml: {e}"),
+            Self::PrefixMismatch { prefix, expected } => write!(
+                f,
+                "archive directory `{prefix}` does not match the manifest (`{expected}`)"
+            ),
+            Self::Readme(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::ManifestEncoding(e) => Some(e),
+            Self::Manifest(e) => Some(e),
+            Self::Readme(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// The contents of a `.crate` file.
+///
+/// The whole archive is decompressed into memory when it is read.
+#[derive(Debug, Clone)]
+pub struct DotCrate {
+    checksum: [u8; 32],
+    prefix: String,
+    manifest: String,
+    // Keyed by path relative to `prefix`, using `/` as the separator.
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl DotCrate {
+    /// Reads the `.crate` file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReadError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Reads a `.crate` file from its raw (compressed) bytes, with the default [`Limits`].
+    ///
+    /// Only regular files in the archive are kept. Every path must be inside the
+    /// `<name>-<version>/` directory, and that directory must contain a `Cargo.toml` whose name and
+    /// version match the directory name.
+    pub fn from_bytes(dotcrate: &[u8]) -> Result<Self, ReadError> {
+        Self::from_bytes_with(dotcrate, Limits::default())
+    }
+
+    /// Like [`DotCrate::from_bytes`], but fails with [`ReadError::TooLarge`] if the archive
+    /// decompresses to more than [`Limits::max_unpacked_size`] bytes.
+    pub fn from_bytes_with(dotcrate: &[u8], limits: Limits) -> Result<Self, ReadError> {
+        let mut archive = tar::Archive::new(Limited::new(
+            GzDecoder::new(dotcrate),
+            limits.max_unpacked_size,
+        ));
+        let (prefix, mut files) = match Self::unpack(&mut archive) {
+            Err(ReadError::Io(_)) if archive.into_inner().exceeded => {
+                return Err(ReadError::TooLarge {
+                    limit: limits.max_unpacked_size,
+                })
+            }
+            unpacked => unpacked?,
+        };
+        let manifest = files
+            .remove("Cargo.toml")
+            .ok_or(ReadError::MissingManifest)?;
+        let manifest =
+            String::from_utf8(manifest).map_err(|e| ReadError::ManifestEncoding(e.utf8_error()))?;
+
+        #[derive(Deserialize)]
+        struct Manifest {
+            package: Package,
+        }
+        #[derive(Deserialize)]
+        struct Package {
+            name: String,
+            #[serde(deserialize_with = "super::deser::version_trim_whitespace")]
+            version: semver::Version,
+        }
+        let Manifest { package } =
+            toml_edit::de::from_str(&manifest).map_err(ReadError::Manifest)?;
+        let expected = format!("{}-{}", package.name, package.version);
+        if prefix != expected {
+            return Err(ReadError::PrefixMismatch { prefix, expected });
+        }
+
+        Ok(Self {
+            checksum: checksum(dotcrate),
+            prefix,
+            manifest,
+            files,
+        })
+    }
+
+    /// Reads every regular file in `archive`, returning the top-level directory and the files
+    /// keyed by their path relative to it.
+    fn unpack(
+        archive: &mut tar::Archive<impl Read>,
+    ) -> Result<(String, BTreeMap<String, Vec<u8>>), ReadError> {
+        let mut prefix: Option<String> = None;
+        let mut files = BTreeMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let mut components = path.components();
+            let top = match components.next() {
+                Some(Component::Normal(top)) => top.to_string_lossy(),
+                _ => return Err(ReadError::OutsidePrefix(path.display().to_string())),
+            };
+            match &prefix {
+                None => prefix = Some(top.into_owned()),
+                Some(prefix) if **prefix == *top => {}
+                Some(_) => return Err(ReadError::OutsidePrefix(path.display().to_string())),
+            }
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let mut relative = Vec::new();
+            for c in components {
+                match c {
+                    Component::Normal(c) => relative.push(c.to_string_lossy()),
+                    _ => return Err(ReadError::OutsidePrefix(path.display().to_string())),
+                }
+            }
+            if relative.is_empty() {
+                return Err(ReadError::OutsidePrefix(path.display().to_string()));
+            }
+            // The size in the header is not trusted, so it is not used to allocate up front.
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            files.insert(relative.join("/"), contents);
+        }
+        Ok((prefix.ok_or(ReadError::MissingManifest)?, files))
+    }
+
+    /// The SHA-256 checksum of the `.crate` file.
+    pub fn checksum(&self) -> [u8; 32] {
+        self.checksum
+    }
+
+    /// The top-level directory of the archive, which is `<name>-<version>`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The normalized `Cargo.toml` as it appears in the archive.
+    pub fn manifest_str(&self) -> &str {
+        &self.manifest
+    }
+
+    /// Parses the normalized `Cargo.toml` in the archive.
+    pub fn manifest<Name, Feature>(&self) -> Result<NormalizedManifest<Name, Feature>, ReadError>
+    where
+        Name: DeserializeOwned,
+        Feature: DeserializeOwned + Ord,
+    {
+        toml_edit::de::from_str(&self.manifest).map_err(ReadError::Manifest)
+    }
+
+    /// The contents of the file at `path`, relative to the archive's top-level directory.
+    ///
+    /// `Cargo.toml` is not included; use [`DotCrate::manifest_str`] instead.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(|f| &**f)
+    }
+
+    /// All files in the archive other than `Cargo.toml`, in path order.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.files.iter().map(|(p, c)| (&**p, &**c))
+    }
+
+    /// The `Cargo.toml` as it was written by the author, before cargo normalized it.
+    pub fn original_manifest(&self) -> Option<&[u8]> {
+        self.file("Cargo.toml.orig")
+    }
+
+    /// Information about the VCS commit the crate was packaged from, if cargo recorded it.
+    pub fn vcs_info(&self) -> Option<&[u8]> {
+        self.file(".cargo_vcs_info.json")
+    }
+
+    /// The path and contents of the package's readme.
+    ///
+    /// See [`resolve_readme`](crate::publish::resolve_readme) for how the readme is determined.
+    /// Returns `None` if there is no readme, or if the readme named by `Cargo.toml` is missing.
+    pub fn readme(&self) -> Result<Option<(&str, &[u8])>, ReadError> {
+        let m: NormalizedManifest<String, String> = self.manifest()?;
+        let path =
+            crate::publish::readme_path(m.package.readme.as_ref(), |p| self.files.contains_key(p));
+        Ok(path.and_then(|p| self.files.get_key_value(&p).map(|(p, c)| (&**p, &**c))))
+    }
+}
@@ -0,0 +1,272 @@
+use flate2::read::GzDecoder;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+/// Limits on the contents of a `.crate` file checked by [`validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The most bytes the archive may decompress to.
+    ///
+    /// Defaults to 512 MiB, which is what crates.io uses.
+    pub max_unpacked_size: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_unpacked_size: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// A problem with a `.crate` file found by [`validate`].
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The archive could not be read, or is not a gzip-compressed tarball.
+    Io(io::Error),
+    /// The archive decompresses to more than [`Limits::max_unpacked_size`] bytes.
+    TooLarge {
+        /// The limit that was exceeded.
+        limit: u64,
+    },
+    /// A path is absolute.
+    AbsolutePath(String),
+    /// A path contains a `..` component.
+    PathTraversal(String),
+    /// A path is not inside the `<name>-<version>/` directory.
+    OutsidePrefix(String),
+    /// A symbolic link or hard link points outside the `<name>-<version>/` directory.
+    EscapingLink {
+        /// The path of the link.
+        path: String,
+        /// The target of the link.
+        target: String,
+    },
+    /// An entry is neither a regular file, a directory, nor a link.
+    UnsupportedEntry {
+        /// The path of the entry.
+        path: String,
+        /// The tar entry type.
+        kind: tar::EntryType,
+    },
+    /// Two paths differ only in case, and so would collide on case-insensitive file systems.
+    CaseConflict(String, String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::TooLarge { limit } => {
+                write!(f, "archive decompresses to more than {limit} bytes")
+            }
+            Self::AbsolutePath(path) => write!(f, "`{path}` is an absolute path"),
+            Self::PathTraversal(path) => write!(f, "`{path}` contains `..`"),
+            Self::OutsidePrefix(path) => {
+                write!(f, "`{path}` is outside the package directory")
+            }
+            Self::EscapingLink { path, target } => write!(
+                f,
+                "link `{path}` points to `{target}`, which is outside the package directory"
+            ),
+            Self::UnsupportedEntry { path, kind } => {
+                write!(f, "`{path}` has unsupported entry type {kind:?}")
+            }
+            Self::CaseConflict(a, b) => write!(f, "`{a}` and `{b}` differ only in case"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Counts decompressed bytes, and fails once there are too many of them.
+pub(super) struct Limited<R> {
+    inner: R,
+    remaining: u64,
+    pub(super) exceeded: bool,
+}
+
+impl<R> Limited<R> {
+    pub(super) fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            exceeded: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        match self.remaining.checked_sub(n as u64) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                Ok(n)
+            }
+            None => {
+                self.exceeded = true;
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "archive is too large",
+                ))
+            }
+        }
+    }
+}
+
+/// Resolves `path` within the archive, following the symbolic links in `links`.
+///
+/// `path` is relative to `base` (itself relative to the root of the archive). `links` maps each
+/// symbolic link seen so far to what it resolves to. Returns `None` if resolving the path goes
+/// above the root of the archive.
+fn resolve(
+    links: &BTreeMap<Vec<String>, Vec<String>>,
+    base: &[&str],
+    path: &Path,
+) -> Option<Vec<String>> {
+    let mut resolved = Vec::new();
+    let base = base.iter().map(|c| Component::Normal(OsStr::new(c)));
+    for c in base.chain(path.components()) {
+        match c {
+            Component::Normal(c) => {
+                resolved.push(c.to_str()?.to_string());
+                if let Some(target) = links.get(&resolved) {
+                    resolved = target.clone();
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Checks that a `.crate` file is safe to extract.
+///
+/// `name` and `version` are those the crate claims to be (such as in the publish request), and
+/// determine the `<name>-<version>/` directory that every path in the archive must be inside.
+///
+/// Every problem found is reported, not just the first one, except that reading stops at the
+/// first [`ValidationError::Io`] or [`ValidationError::TooLarge`].
+pub fn validate(
+    dotcrate: impl Read,
+    name: &str,
+    version: &semver::Version,
+    limits: Limits,
+) -> Result<(), Vec<ValidationError>> {
+    let prefix = format!("{name}-{version}");
+    let mut errors = Vec::new();
+    let mut archive = tar::Archive::new(Limited::new(
+        GzDecoder::new(dotcrate),
+        limits.max_unpacked_size,
+    ));
+    // Lower-cased path -> the first path seen with that spelling.
+    let mut seen = BTreeMap::new();
+    // Symbolic link -> what it resolves to, both as components from the root of the archive.
+    let mut links = BTreeMap::new();
+
+    let result = (|| -> io::Result<()> {
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let kind = entry.header().entry_type();
+            let path = entry.path()?.into_owned();
+            let display = path.display().to_string();
+
+            if path.has_root() || path.components().any(|c| matches!(c, Component::Prefix(_))) {
+                errors.push(ValidationError::AbsolutePath(display));
+                continue;
+            }
+            if path.components().any(|c| c == Component::ParentDir) {
+                errors.push(ValidationError::PathTraversal(display));
+                continue;
+            }
+            let components: Option<Vec<_>> = path
+                .components()
+                .filter(|c| *c != Component::CurDir)
+                .map(|c| c.as_os_str().to_str())
+                .collect();
+            let components = match components {
+                Some(components) if components.first() == Some(&&*prefix) => components,
+                _ => {
+                    errors.push(ValidationError::OutsidePrefix(display));
+                    continue;
+                }
+            };
+
+            if kind.is_symlink() || kind.is_hard_link() {
+                let target = entry.link_name()?.unwrap_or_default().into_owned();
+                let (parent, name) = components.split_at(components.len() - 1);
+                let resolved = if kind.is_symlink() {
+                    // Symbolic links are relative to the directory that holds them.
+                    resolve(&links, parent, &target)
+                } else {
+                    // Hard links are relative to the root of the archive.
+                    resolve(&links, &[], &target)
+                };
+                match resolved {
+                    Some(resolved) if resolved.first() == Some(&prefix) => {
+                        // Later links may go through this one, and must be resolved accordingly.
+                        if kind.is_symlink() {
+                            if let Some(mut path) = resolve(&links, parent, Path::new("")) {
+                                path.push(name[0].to_string());
+                                links.insert(path, resolved);
+                            }
+                        }
+                    }
+                    _ => errors.push(ValidationError::EscapingLink {
+                        path: display.clone(),
+                        target: target.display().to_string(),
+                    }),
+                }
+            } else if !(kind.is_file() || kind.is_dir() || kind.is_pax_global_extensions()) {
+                errors.push(ValidationError::UnsupportedEntry {
+                    path: display,
+                    kind,
+                });
+                continue;
+            }
+
+            let normalized = components.join("/");
+            match seen.entry(normalized.to_lowercase()) {
+                Entry::Vacant(e) => {
+                    e.insert(normalized);
+                }
+                Entry::Occupied(e) if *e.get() != normalized => {
+                    errors.push(ValidationError::CaseConflict(e.get().clone(), normalized));
+                }
+                Entry::Occupied(_) => {}
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        if archive.into_inner().exceeded {
+            errors.push(ValidationError::TooLarge {
+                limit: limits.max_unpacked_size,
+            });
+        } else {
+            errors.push(ValidationError::Io(e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,191 @@
+use super::NormalizedManifest;
+use flate2::{Compression, GzBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+/// The mtime `tar::HeaderMode::Deterministic` (and thus cargo) uses for files copied from disk.
+const DETERMINISTIC_MTIME: u64 = 1153704088;
+
+/// The mtime cargo uses for the files it generates.
+///
+/// It is non-zero to avoid <https://github.com/rust-lang/cargo/issues/9512>.
+const GENERATED_MTIME: u64 = 1;
+
+/// Files that `cargo package` generates rather than copying from the package source.
+const GENERATED: &[&str] = &["Cargo.toml", "Cargo.lock", ".cargo_vcs_info.json"];
+
+/// An error from building a `.crate` file.
+#[derive(Debug)]
+pub enum WriteError {
+    /// A file path is empty, absolute, or contains `.` or `..` components.
+    InvalidPath(String),
+    /// The manifest could not be serialized.
+    Manifest(toml_edit::ser::Error),
+    /// The archive could not be written.
+    Io(io::Error),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPath(path) => write!(f, "invalid path `{path}`"),
+            Self::Manifest(e) => write!(f, "failed to serialize Cargo.toml: {e}"),
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Manifest(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::InvalidPath(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct File {
+    contents: Vec<u8>,
+    executable: bool,
+}
+
+/// Builds `.crate` files the way `cargo package` does.
+///
+/// The output only depends on the manifest and the files that were added, so building the same
+/// crate twice produces the same checksum. Specifically, like cargo:
+///
+/// - every file is placed under a `<name>-<version>/` directory;
+/// - `Cargo.toml` is generated from the manifest by [`NormalizedManifest::to_toml`], replacing
+///   any `Cargo.toml` that was added as a file;
+/// - files are ordered by path;
+/// - `Cargo.toml`, `Cargo.lock`, and `.cargo_vcs_info.json` get mode `0o644` and an mtime of 1;
+/// - all other files get mode `0o644` (or `0o755` if executable), owner 0, and the fixed mtime of
+///   `tar::HeaderMode::Deterministic`;
+/// - the archive is compressed with the best gzip compression, and the gzip header records only
+///   the `<name>-<version>.crate` file name.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    // Keyed by path relative to the `<name>-<version>` directory.
+    files: BTreeMap<String, File>,
+}
+
+impl Builder {
+    /// Creates a builder with no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a regular file at `path`, relative to the package root and using `/` as the separator.
+    ///
+    /// `Cargo.toml` is always generated from the manifest passed to [`Builder::build`], so a file
+    /// added at that path is ignored. The author's original manifest goes in `Cargo.toml.orig`.
+    pub fn file(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) -> &mut Self {
+        self.insert(path.into(), contents.into(), false)
+    }
+
+    /// Adds an executable file at `path`.
+    ///
+    /// See [`Builder::file`].
+    pub fn executable(
+        &mut self,
+        path: impl Into<String>,
+        contents: impl Into<Vec<u8>>,
+    ) -> &mut Self {
+        self.insert(path.into(), contents.into(), true)
+    }
+
+    fn insert(&mut self, path: String, contents: Vec<u8>, executable: bool) -> &mut Self {
+        self.files.insert(
+            path,
+            File {
+                contents,
+                executable,
+            },
+        );
+        self
+    }
+
+    /// Builds the `.crate` file for `manifest` in memory.
+    pub fn build<Name, Feature>(
+        &self,
+        manifest: &NormalizedManifest<Name, Feature>,
+    ) -> Result<Vec<u8>, WriteError>
+    where
+        Name: AsRef<str> + Serialize,
+        Feature: Ord + Serialize,
+    {
+        self.write_to(manifest, Vec::new())
+    }
+
+    /// Writes the `.crate` file for `manifest` to `w`.
+    pub fn write_to<Name, Feature, W>(
+        &self,
+        manifest: &NormalizedManifest<Name, Feature>,
+        w: W,
+    ) -> Result<W, WriteError>
+    where
+        Name: AsRef<str> + Serialize,
+        Feature: Ord + Serialize,
+        W: Write,
+    {
+        let base = format!(
+            "{}-{}",
+            manifest.package.name.as_ref(),
+            manifest.package.version
+        );
+        let toml = manifest.to_toml().map_err(WriteError::Manifest)?;
+
+        let mut files: Vec<(&str, &[u8], bool)> = self
+            .files
+            .iter()
+            .filter(|(path, _)| *path != "Cargo.toml")
+            .map(|(path, f)| (&**path, &*f.contents, f.executable))
+            .collect();
+        files.push(("Cargo.toml", toml.as_bytes(), false));
+        for (path, _, _) in &files {
+            if path.is_empty()
+                || path.starts_with('/')
+                || path.contains('\\')
+                || path
+                    .split('/')
+                    .any(|c| c.is_empty() || c == "." || c == "..")
+            {
+                return Err(WriteError::InvalidPath(path.to_string()));
+            }
+        }
+        // cargo sorts by `Path`, which compares component-wise.
+        files.sort_unstable_by(|(a, _, _), (b, _, _)| a.split('/').cmp(b.split('/')));
+
+        let encoder = GzBuilder::new()
+            .filename(format!("{base}.crate"))
+            .write(w, Compression::best());
+        let mut ar = tar::Builder::new(encoder);
+        for (path, contents, executable) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(contents.len() as u64);
+            if GENERATED.contains(&path) {
+                header.set_mode(0o644);
+                header.set_mtime(GENERATED_MTIME);
+            } else {
+                header.set_mode(if executable { 0o755 } else { 0o644 });
+                header.set_uid(0);
+                header.set_gid(0);
+                header.set_mtime(DETERMINISTIC_MTIME);
+            }
+            header.set_cksum();
+            ar.append_data(&mut header, format!("{base}/{path}"), contents)?;
+        }
+        Ok(ar.into_inner()?.finish()?)
+    }
+}
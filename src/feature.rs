@@ -0,0 +1,206 @@
+//! The values of the `[features]` table.
+//!
+//! <https://doc.rust-lang.org/cargo/reference/features.html>
+
+use crate::index::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+mod resolve;
+pub use resolve::{DependencyFeatures, Resolution, ResolveError};
+
+/// A feature table with each value parsed.
+pub type FeatureMap<'a> = BTreeMap<&'a str, Vec<FeatureValue<'a>>>;
+
+/// One of the things a feature enables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FeatureValue<'a> {
+    /// Another feature of the same package, like `std`.
+    Feature(&'a str),
+    /// An optional dependency, without enabling the implicit feature of the same name, like
+    /// `dep:serde`.
+    Dep {
+        /// The name of the dependency (as it is called in `Cargo.toml`).
+        dep_name: &'a str,
+    },
+    /// A feature of a dependency, like `serde/derive` or `serde?/derive`.
+    ///
+    /// If the dependency is optional, `serde/derive` also enables it, whereas the weak form
+    /// `serde?/derive` only enables the feature if something else enables the dependency.
+    DepFeature {
+        /// The name of the dependency (as it is called in `Cargo.toml`).
+        dep_name: &'a str,
+        /// The feature of the dependency to enable.
+        dep_feature: &'a str,
+        /// Whether this is the weak (`?/`) form.
+        weak: bool,
+    },
+}
+
+impl<'a> FeatureValue<'a> {
+    /// Parses a feature value the way cargo does.
+    ///
+    /// Any string is a feature value; whether it names features and dependencies that exist is
+    /// not checked. [`Display`](fmt::Display) gives back the original string.
+    pub fn new(value: &'a str) -> Self {
+        match value.split_once('/') {
+            Some((dep_name, dep_feature)) => match dep_name.strip_suffix('?') {
+                Some(dep_name) => Self::DepFeature {
+                    dep_name,
+                    dep_feature,
+                    weak: true,
+                },
+                None => Self::DepFeature {
+                    dep_name,
+                    dep_feature,
+                    weak: false,
+                },
+            },
+            None => match value.strip_prefix("dep:") {
+                Some(dep_name) => Self::Dep { dep_name },
+                None => Self::Feature(value),
+            },
+        }
+    }
+
+    /// Whether this value uses syntax that cargo before 1.60 does not understand (`dep:` or
+    /// `?/`).
+    ///
+    /// Features with such values go in [`Entry::features2`](crate::index::Entry::features2). This
+    /// is the same test crates.io uses (the value starts with `dep:` or contains `?/`), so it also
+    /// holds for malformed values like `dep:serde/derive`.
+    pub fn requires_features2(&self) -> bool {
+        match self {
+            Self::Feature(_) => false,
+            Self::Dep { .. } => true,
+            Self::DepFeature {
+                dep_name,
+                dep_feature,
+                weak,
+            } => *weak || dep_name.starts_with("dep:") || dep_feature.contains("?/"),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for FeatureValue<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Display for FeatureValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Feature(feature) => f.write_str(feature),
+            Self::Dep { dep_name } => write!(f, "dep:{dep_name}"),
+            Self::DepFeature {
+                dep_name,
+                dep_feature,
+                weak,
+            } => write!(
+                f,
+                "{dep_name}{}/{dep_feature}",
+                if *weak { "?" } else { "" }
+            ),
+        }
+    }
+}
+
+/// Parses every value of the feature tables `tables`, merging them.
+pub(crate) fn parse_tables<'a, Feature>(
+    tables: impl IntoIterator<Item = &'a BTreeMap<Feature, Vec<Feature>>>,
+) -> FeatureMap<'a>
+where
+    Feature: AsRef<str> + 'a,
+{
+    // A feature in more than one table gets the values from all of them, as cargo does for
+    // `features` and `features2`.
+    let mut features = FeatureMap::new();
+    for (k, vs) in tables.into_iter().flatten() {
+        features
+            .entry(k.as_ref())
+            .or_default()
+            .extend(vs.iter().map(|v| FeatureValue::new(v.as_ref())));
+    }
+    features
+}
+
+/// The features of a package as cargo sees them, including implicit features.
+///
+/// See [`Entry::effective_features`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureTable<'a> {
+    /// Every feature, explicit or implicit.
+    pub features: FeatureMap<'a>,
+    /// The features in [`features`](Self::features) that are implicit.
+    ///
+    /// Cargo creates an implicit feature, with the same name, for every optional dependency that
+    /// is not referred to by any `dep:` value. It enables just that dependency (`x = ["dep:x"]`).
+    pub implicit: BTreeSet<&'a str>,
+}
+
+impl<'a> FeatureTable<'a> {
+    /// Adds the implicit features of the `optional` dependencies to the explicit `features`.
+    pub(crate) fn new(
+        mut features: FeatureMap<'a>,
+        optional: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        let hidden: BTreeSet<_> = features
+            .values()
+            .flatten()
+            .filter_map(|v| match v {
+                FeatureValue::Dep { dep_name } => Some(*dep_name),
+                _ => None,
+            })
+            .collect();
+        let mut implicit = BTreeSet::new();
+        for name in optional {
+            if hidden.contains(name) || features.contains_key(name) {
+                continue;
+            }
+            features.insert(name, vec![FeatureValue::Dep { dep_name: name }]);
+            implicit.insert(name);
+        }
+        Self { features, implicit }
+    }
+
+    /// The features as they would be written if every implicit feature were made explicit.
+    ///
+    /// This is the same set of features as [`features`](Self::features), but in the form cargo's
+    /// migration away from implicit features produces: each optional dependency that had an
+    /// implicit feature `x` gets the explicit feature `x = ["dep:x"]`. Such a table can be
+    /// written to `Cargo.toml` (or `features2`) without changing which features exist.
+    pub fn migrated(&self) -> BTreeMap<String, Vec<String>> {
+        self.features
+            .iter()
+            .map(|(k, vs)| (k.to_string(), vs.iter().map(|v| v.to_string()).collect()))
+            .collect()
+    }
+
+    /// Only the explicit features, as they appear in the index or `Cargo.toml`.
+    pub fn explicit(&self) -> FeatureMap<'_> {
+        self.features
+            .iter()
+            .filter(|(k, _)| !self.implicit.contains(*k))
+            .map(|(k, vs)| (*k, vs.clone()))
+            .collect()
+    }
+}
+
+impl<Name, Version, Req, Feature, Target, Links> Entry<Name, Version, Req, Feature, Target, Links>
+where
+    Name: AsRef<str>,
+    Feature: Ord + AsRef<str>,
+{
+    /// The features of this version as cargo sees them, including the implicit feature of every
+    /// optional dependency that is not referred to by a `dep:` value.
+    pub fn effective_features(&self) -> FeatureTable<'_> {
+        FeatureTable::new(
+            self.feature_values(),
+            self.dependencies
+                .iter()
+                .filter(|d| d.optional)
+                .map(|d| d.name.as_ref()),
+        )
+    }
+}
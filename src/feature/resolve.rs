@@ -0,0 +1,178 @@
+use super::{FeatureMap, FeatureValue};
+use crate::index::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The outcome of [`Entry::resolve_features`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolution<'a> {
+    /// The features of the package that are activated.
+    pub features: BTreeSet<&'a str>,
+    /// The optional dependencies that are enabled, by the name they have in `Cargo.toml`.
+    pub optional_dependencies: BTreeSet<&'a str>,
+    /// Every dependency that is used (that is, every non-optional dependency and every enabled
+    /// optional one), by the name it has in `Cargo.toml`.
+    pub dependencies: BTreeMap<&'a str, DependencyFeatures<'a>>,
+}
+
+/// The features a dependency is built with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyFeatures<'a> {
+    /// The features of the dependency that are activated, other than `default`.
+    ///
+    /// This includes both those listed with the dependency and those activated through
+    /// `dep/feature` and `dep?/feature` values.
+    pub features: BTreeSet<&'a str>,
+    /// Whether the dependency's default features are activated.
+    pub default_features: bool,
+}
+
+/// An error from [`Entry::resolve_features`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// A feature that the package does not have was activated.
+    ///
+    /// This includes the implicit feature of an optional dependency that is hidden by a `dep:`
+    /// value.
+    UnknownFeature(String),
+    /// A feature value refers to a dependency that the package does not have.
+    UnknownDependency(String),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFeature(feature) => write!(f, "package does not have feature `{feature}`"),
+            Self::UnknownDependency(dep) => {
+                write!(f, "package does not have a dependency named `{dep}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+struct Resolver<'a> {
+    table: FeatureMap<'a>,
+    dependencies: BTreeSet<&'a str>,
+    optional: BTreeSet<&'a str>,
+    resolution: Resolution<'a>,
+    // Features of dependencies, whether or not the dependency is (yet) enabled.
+    dependency_features: BTreeMap<&'a str, BTreeSet<&'a str>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn activate(&mut self, value: FeatureValue<'a>) -> Result<(), ResolveError> {
+        // A worklist rather than recursion, so that long chains of features cannot overflow the
+        // stack; values are pushed in reverse so that they are handled in the order listed.
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                FeatureValue::Feature(feature) => {
+                    let values = match self.table.get(feature) {
+                        Some(values) => values,
+                        None => return Err(ResolveError::UnknownFeature(feature.to_string())),
+                    };
+                    if self.resolution.features.insert(feature) {
+                        pending.extend(values.iter().rev().cloned());
+                    }
+                }
+                FeatureValue::Dep { dep_name } => {
+                    self.known_dependency(dep_name)?;
+                    if self.optional.contains(dep_name) {
+                        self.resolution.optional_dependencies.insert(dep_name);
+                    }
+                }
+                FeatureValue::DepFeature {
+                    dep_name,
+                    dep_feature,
+                    weak,
+                } => {
+                    self.known_dependency(dep_name)?;
+                    if !weak && self.optional.contains(dep_name) {
+                        self.resolution.optional_dependencies.insert(dep_name);
+                        // Before weak dependencies, `dep/feature` enabled the dependency through
+                        // its implicit feature, so it still activates a feature of the same name
+                        // if there is one (that is, unless `dep:` hides it).
+                        if self.table.contains_key(dep_name) {
+                            pending.push(FeatureValue::Feature(dep_name));
+                        }
+                    }
+                    // For weak values, this only takes effect if the dependency ends up enabled.
+                    self.dependency_features
+                        .entry(dep_name)
+                        .or_default()
+                        .insert(dep_feature);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn known_dependency(&self, dep_name: &str) -> Result<(), ResolveError> {
+        if self.dependencies.contains(dep_name) {
+            Ok(())
+        } else {
+            Err(ResolveError::UnknownDependency(dep_name.to_string()))
+        }
+    }
+}
+
+impl<Name, Version, Req, Feature, Target, Links> Entry<Name, Version, Req, Feature, Target, Links>
+where
+    Name: AsRef<str>,
+    Feature: Ord + AsRef<str>,
+{
+    /// Works out which features, optional dependencies, and dependency features are activated
+    /// when this version is built with the `requested` features, the way cargo does.
+    ///
+    /// `requested` may include `dep/feature` values, like cargo's `--features`. `default`
+    /// is activated if `default_features` is set and the package has such a feature.
+    ///
+    /// Optional dependencies that are not referred to by any `dep:` value have an implicit
+    /// feature of the same name. A non-weak `dep/feature` value enables an optional dependency
+    /// (and its implicit feature, if it has one), while a weak `dep?/feature` value only applies
+    /// if the dependency is enabled by something else.
+    ///
+    /// Dependencies of every kind and target are considered.
+    pub fn resolve_features<'a>(
+        &'a self,
+        requested: impl IntoIterator<Item = &'a str>,
+        default_features: bool,
+    ) -> Result<Resolution<'a>, ResolveError> {
+        let mut resolver = Resolver {
+            table: self.effective_features().features,
+            dependencies: self.dependencies.iter().map(|d| d.name.as_ref()).collect(),
+            optional: self
+                .dependencies
+                .iter()
+                .filter(|d| d.optional)
+                .map(|d| d.name.as_ref())
+                .collect(),
+            resolution: Resolution::default(),
+            dependency_features: BTreeMap::new(),
+        };
+
+        if default_features && resolver.table.contains_key("default") {
+            resolver.activate(FeatureValue::Feature("default"))?;
+        }
+        for feature in requested {
+            resolver.activate(FeatureValue::new(feature))?;
+        }
+
+        let mut resolution = resolver.resolution;
+        for d in self.dependencies.iter() {
+            let name = d.name.as_ref();
+            if d.optional && !resolution.optional_dependencies.contains(name) {
+                continue;
+            }
+            let used = resolution.dependencies.entry(name).or_default();
+            used.default_features |= d.default_features;
+            used.features.extend(d.features.iter().map(|f| f.as_ref()));
+            if let Some(features) = resolver.dependency_features.get(name) {
+                used.features.extend(features);
+            }
+        }
+        Ok(resolution)
+    }
+}
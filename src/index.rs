@@ -4,6 +4,28 @@ use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+use crate::feature::{FeatureMap, FeatureValue};
+
+mod compat;
+pub use compat::CompatibilityWarning;
+
+mod config;
+pub use config::Config;
+
+mod file;
+pub use file::{read_entries, EditError, Entries, IndexFile, LineError};
+
+mod path;
+pub use path::{
+    name_from_path, path_for, prefix, sparse_url, validate_name, NameError, MAX_NAME_LENGTH,
+};
+
+mod validate;
+pub use validate::ValidationError;
+
+mod url;
+pub use url::{RegistryUrl, CRATES_IO_INDEX, CRATES_IO_SPARSE_INDEX};
+
 /// A single line in the index representing a single version of a package.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Entry<Name, Version, Req, Feature, Target, Links>
@@ -50,6 +72,13 @@ where
     #[serde(skip_serializing_if = "Option::is_none")]
     pub links: Option<Links>,
 
+    /// The minimum supported Rust version (MSRV) of this package, if specified.
+    ///
+    /// Written to the index since cargo 1.70, and used for MSRV-aware resolution. Older versions
+    /// of cargo ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<Box<str>>,
+
     /// The schema version for this entry.
     ///
     /// If this is None, it defaults to version 1. Entries with unknown
@@ -74,6 +103,32 @@ where
     pub schema_version: Option<u8>,
 }
 
+impl<Name, Version, Req, Feature, Target, Links> Entry<Name, Version, Req, Feature, Target, Links>
+where
+    Feature: Ord + AsRef<str>,
+{
+    /// The features of this version (from both `features` and `features2`), with each value
+    /// parsed.
+    pub fn feature_values(&self) -> FeatureMap<'_> {
+        crate::feature::parse_tables(
+            std::iter::once(&*self.features).chain(self.features2.as_deref()),
+        )
+    }
+
+    /// The features in `features` (rather than `features2`) that use the `dep:` or `?/` syntax,
+    /// which cargo before 1.19 fails to parse.
+    pub fn features_with_new_syntax(&self) -> impl Iterator<Item = &str> {
+        self.features
+            .iter()
+            .filter(|(_, values)| {
+                values
+                    .iter()
+                    .any(|v| FeatureValue::new(v.as_ref()).requires_features2())
+            })
+            .map(|(feature, _)| feature.as_ref())
+    }
+}
+
 impl<'a>
     Entry<
         Cow<'a, str>,
@@ -93,7 +148,22 @@ impl<'a>
         Name: Into<Cow<'a, str>>,
         Feature: Into<Cow<'a, str>>,
     {
-        let in_registry = super::publish::CrateVersion::new(v, (None, None), via_registry);
+        let options = super::publish::ConversionOptions::new(via_registry);
+        Self::from_manifest_with(v, &options, checksum)
+    }
+
+    /// Like [`from_manifest`](Self::from_manifest), but decides dependency registries using
+    /// `options`.
+    pub fn from_manifest_with<Name, Feature>(
+        v: super::dotcrate::NormalizedManifest<Name, Feature>,
+        options: &super::publish::ConversionOptions,
+        checksum: [u8; 32],
+    ) -> Self
+    where
+        Name: Into<Cow<'a, str>>,
+        Feature: Ord + Into<Cow<'a, str>>,
+    {
+        let in_registry = super::publish::CrateVersion::new_with(v, (None, None), options);
         Self::from_publish(in_registry, checksum)
     }
 
@@ -102,7 +172,7 @@ impl<'a>
             v.features.into_iter().partition(|(_k, vals)| {
                 !vals
                     .iter()
-                    .any(|v| v.starts_with("dep:") || v.contains("?/"))
+                    .any(|v| FeatureValue::new(v).requires_features2())
             });
         let (features2, schema_version) = if features2.is_empty() {
             (None, None)
@@ -134,7 +204,7 @@ impl<'a>
                                 .map(|r| r.into_owned().into_boxed_str())
                                 .map(Box::new),
                             package,
-                            public: None,
+                            public: d.public,
                         }
                     })
                     .collect::<Vec<_>>()
@@ -145,13 +215,14 @@ impl<'a>
             checksum,
             yanked: false,
             links: v.links.map(Into::into),
+            rust_version: v.rust_version.map(|r| r.into_owned().into_boxed_str()),
             schema_version,
         }
     }
 }
 
 /// A dependency as encoded in the index JSON.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RegistryDependency<Name, Req, Feature, Target> {
     // In old `cargo` versions the dependency order appears to matter if the same dependency exists
     // twice but with different `kind` fields. In those cases the `optional` field can sometimes be
@@ -174,6 +245,11 @@ pub struct RegistryDependency<Name, Req, Feature, Target> {
     pub registry: Option<Box<Box<str>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub package: Option<Box<Name>>,
+    /// Whether this is a public dependency (RFC 1977).
+    ///
+    /// Cargo versions that don't know about public dependencies ignore this field and treat the
+    /// dependency as private, so its presence does not require bumping the entry's
+    /// `schema_version`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub public: Option<bool>,
 }
@@ -0,0 +1,119 @@
+use super::Entry;
+use std::fmt;
+
+/// The cargo version that added dependency renaming (`package`).
+const RENAMED_DEPENDENCIES: semver::Version = semver::Version::new(1, 31, 0);
+
+/// The cargo version that added alternative registries (`registry`).
+const ALTERNATIVE_REGISTRIES: semver::Version = semver::Version::new(1, 34, 0);
+
+/// The cargo version that started honoring `schema_version`.
+const SCHEMA_VERSION: semver::Version = semver::Version::new(1, 51, 0);
+
+/// The cargo version that added namespaced (`dep:`) and weak (`?/`) features, and `features2`.
+const NEW_FEATURE_SYNTAX: semver::Version = semver::Version::new(1, 60, 0);
+
+/// The cargo version before which the new feature syntax in `features` breaks loading the crate.
+const FEATURE_PARSING: semver::Version = semver::Version::new(1, 19, 0);
+
+/// The newest `schema_version` that cargo understands.
+const MAX_SCHEMA_VERSION: u8 = 2;
+
+/// A way in which an old version of cargo would misread an [`Entry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompatibilityWarning {
+    /// Cargo before 1.51 ignores `schema_version`, so rather than skipping this entry, it uses
+    /// it without the features in `features2`.
+    Features2Ignored,
+    /// The named feature in `features` uses `dep:` or `?/`, which cargo before 1.19 cannot parse.
+    /// Such versions of cargo fail to load any version of the crate, even with a `Cargo.lock`.
+    NewSyntaxInFeatures(String),
+}
+
+impl CompatibilityWarning {
+    /// The first cargo version that is not affected.
+    pub fn fixed_in(&self) -> semver::Version {
+        match self {
+            Self::Features2Ignored => SCHEMA_VERSION,
+            Self::NewSyntaxInFeatures(_) => FEATURE_PARSING,
+        }
+    }
+}
+
+impl fmt::Display for CompatibilityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Features2Ignored => write!(
+                f,
+                "cargo older than {} ignores `v` and reads this entry without its `features2`",
+                self.fixed_in()
+            ),
+            Self::NewSyntaxInFeatures(feature) => write!(
+                f,
+                "cargo older than {} cannot parse feature `{feature}` and fails to load the crate",
+                self.fixed_in()
+            ),
+        }
+    }
+}
+
+impl<Name, Version, Req, Feature, Target, Links> Entry<Name, Version, Req, Feature, Target, Links>
+where
+    Feature: Ord + AsRef<str>,
+{
+    /// The oldest cargo version that correctly uses this entry.
+    ///
+    /// - Dependency renames (`package`) need cargo 1.31.
+    /// - Dependencies from other registries (`registry`) need cargo 1.34.
+    /// - `features2` and the `dep:` and `?/` feature syntax need cargo 1.60. Cargo from 1.51
+    ///   skips entries with a `schema_version` it does not understand, which is safe; see
+    ///   [`compatibility_warnings`](Self::compatibility_warnings) for older versions.
+    ///
+    /// `rust_version` and [`RegistryDependency::public`](super::RegistryDependency::public) do
+    /// not affect the result, since cargo versions that do not know them ignore them. Artifact
+    /// dependencies are unstable, and not represented in [`Entry`].
+    ///
+    /// Returns `None` if the `schema_version` is newer than any cargo understands, in which case
+    /// every cargo from 1.51 skips the entry.
+    pub fn min_cargo_version(&self) -> Option<semver::Version> {
+        if self.schema_version.unwrap_or(1) > MAX_SCHEMA_VERSION {
+            return None;
+        }
+        let mut min = semver::Version::new(1, 0, 0);
+        let mut require = |v: semver::Version| {
+            if v > min {
+                min = v;
+            }
+        };
+        if self.dependencies.iter().any(|d| d.package.is_some()) {
+            require(RENAMED_DEPENDENCIES);
+        }
+        if self.dependencies.iter().any(|d| d.registry.is_some()) {
+            require(ALTERNATIVE_REGISTRIES);
+        }
+        if self.features_with_new_syntax().next().is_some()
+            || self.features2.as_ref().map_or(false, |f| !f.is_empty())
+        {
+            require(NEW_FEATURE_SYNTAX);
+        }
+        Some(min)
+    }
+
+    /// The ways in which cargo as old as `oldest` would misread this entry.
+    ///
+    /// Cargo older than [`min_cargo_version`](Self::min_cargo_version) cannot use the entry
+    /// correctly in any case; these are the cases where it does not notice, and so picks this
+    /// version anyway or fails altogether.
+    pub fn compatibility_warnings(&self, oldest: &semver::Version) -> Vec<CompatibilityWarning> {
+        let mut warnings = Vec::new();
+        if self.features2.as_ref().map_or(false, |f| !f.is_empty()) {
+            warnings.push(CompatibilityWarning::Features2Ignored);
+        }
+        warnings.extend(
+            self.features_with_new_syntax()
+                .map(|f| CompatibilityWarning::NewSyntaxInFeatures(f.to_string())),
+        );
+        warnings.retain(|w| *oldest < w.fixed_in());
+        warnings
+    }
+}
@@ -0,0 +1,82 @@
+use super::Entry;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+const CRATE_TEMPLATE: &str = "{crate}";
+const VERSION_TEMPLATE: &str = "{version}";
+const PREFIX_TEMPLATE: &str = "{prefix}";
+const LOWER_PREFIX_TEMPLATE: &str = "{lowerprefix}";
+const CHECKSUM_TEMPLATE: &str = "{sha256-checksum}";
+
+/// The `config.json` file at the root of a registry index.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    /// The template for crate download URLs.
+    ///
+    /// See [`Config::download_url`] for how it is expanded.
+    pub dl: String,
+
+    /// The base URL of the registry's web API.
+    ///
+    /// If this is `None`, the registry does not support operations like publishing or yanking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+
+    /// Whether cargo must authenticate all requests to the registry, including downloads and index
+    /// fetches.
+    ///
+    /// <https://rust-lang.github.io/rfcs/3139-cargo-alternative-registry-auth.html>
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auth_required: bool,
+}
+
+impl Config {
+    /// The URL cargo downloads the `.crate` file for `entry` from.
+    ///
+    /// The following markers in [`Config::dl`] are replaced:
+    ///
+    /// - `{crate}`: the name of the crate.
+    /// - `{version}`: the version of the crate.
+    /// - `{prefix}`: the index directory of the crate (see [`super::prefix`]).
+    /// - `{lowerprefix}`: same as `{prefix}`, but lower-cased.
+    /// - `{sha256-checksum}`: the hex-encoded checksum of the `.crate` file.
+    ///
+    /// If none of the markers are present, `/{crate}/{version}/download` is appended instead, which
+    /// was the only supported format before the markers were introduced.
+    ///
+    /// The name of `entry` is not validated, so this works (if not usefully) for names read from a
+    /// malformed index too; use [`super::validate_name`] to reject those first.
+    pub fn download_url<Name, Version, Req, Feature, Target, Links>(
+        &self,
+        entry: &Entry<Name, Version, Req, Feature, Target, Links>,
+    ) -> String
+    where
+        Name: AsRef<str>,
+        Version: Display,
+        Feature: Ord,
+    {
+        let name = entry.name.as_ref();
+        let version = entry.version.to_string();
+        let url = &self.dl;
+        if ![
+            CRATE_TEMPLATE,
+            VERSION_TEMPLATE,
+            PREFIX_TEMPLATE,
+            LOWER_PREFIX_TEMPLATE,
+            CHECKSUM_TEMPLATE,
+        ]
+        .iter()
+        .any(|t| url.contains(t))
+        {
+            return format!("{url}/{name}/{version}/download");
+        }
+
+        let prefix = super::prefix(name);
+        url.replace(CRATE_TEMPLATE, name)
+            .replace(VERSION_TEMPLATE, &version)
+            .replace(PREFIX_TEMPLATE, &prefix)
+            .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase())
+            .replace(CHECKSUM_TEMPLATE, &hex::encode(entry.checksum))
+    }
+}
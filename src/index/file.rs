@@ -0,0 +1,344 @@
+use super::{Entry, RegistryDependency};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// An error in a single line of an index file.
+#[derive(Debug)]
+pub struct LineError {
+    line: usize,
+    error: serde_json::Error,
+}
+
+impl LineError {
+    /// The (1-based) line number of the offending line.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for LineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Reads every [`Entry`] from the contents of a single index file.
+///
+/// Index files hold one JSON-encoded entry per line. Lines that fail to parse are yielded as
+/// errors without ending iteration, and blank lines are skipped.
+///
+/// Entries whose dependency lists or feature maps are identical to those of an entry that was
+/// read earlier share the same `Arc` rather than holding their own copy.
+pub fn read_entries<Name, Version, Req, Feature, Target, Links>(
+    contents: &str,
+) -> Entries<'_, Name, Version, Req, Feature, Target, Links>
+where
+    Feature: Ord,
+{
+    Entries {
+        lines: contents.lines().enumerate(),
+        dependencies: HashSet::new(),
+        features: HashSet::new(),
+        _entry: std::marker::PhantomData,
+    }
+}
+
+type Dependencies<Name, Req, Feature, Target> =
+    Arc<[RegistryDependency<Name, Req, Feature, Target>]>;
+
+/// Iterator over the entries of an index file.
+///
+/// Constructed with [`read_entries`].
+pub struct Entries<'a, Name, Version, Req, Feature, Target, Links>
+where
+    Feature: Ord,
+{
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+
+    // Every distinct list seen so far.
+    dependencies: HashSet<Dependencies<Name, Req, Feature, Target>>,
+    features: HashSet<Arc<BTreeMap<Feature, Vec<Feature>>>>,
+
+    _entry: std::marker::PhantomData<fn() -> (Version, Links)>,
+}
+
+impl<'a, Name, Version, Req, Feature, Target, Links> Iterator
+    for Entries<'a, Name, Version, Req, Feature, Target, Links>
+where
+    Entry<Name, Version, Req, Feature, Target, Links>: Deserialize<'a>,
+    Name: Eq + Hash,
+    Req: Eq + Hash,
+    Feature: Ord + Hash,
+    Target: Eq + Hash,
+{
+    type Item = Result<Entry<Name, Version, Req, Feature, Target, Links>, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, line) = self
+            .lines
+            .by_ref()
+            .find(|(_, line)| !line.trim().is_empty())?;
+
+        let mut entry: Entry<Name, Version, Req, Feature, Target, Links> =
+            match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(error) => return Some(Err(LineError { line: i + 1, error })),
+            };
+
+        entry.dependencies = dedup(&mut self.dependencies, entry.dependencies);
+        entry.features = dedup(&mut self.features, entry.features);
+        Some(Ok(entry))
+    }
+}
+
+fn dedup<T: ?Sized + Eq + Hash>(seen: &mut HashSet<Arc<T>>, v: Arc<T>) -> Arc<T> {
+    if let Some(same) = seen.get(&v) {
+        Arc::clone(same)
+    } else {
+        seen.insert(Arc::clone(&v));
+        v
+    }
+}
+
+/// An error from editing an [`IndexFile`].
+#[derive(Debug)]
+pub enum EditError {
+    /// The file already holds this version, possibly with different build metadata.
+    AlreadyExists(semver::Version),
+    /// The file holds no entry with this version.
+    NotFound(semver::Version),
+    /// An entry could not be serialized or deserialized.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyExists(v) => write!(f, "version {v} already exists"),
+            Self::NotFound(v) => write!(f, "version {v} does not exist"),
+            Self::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for EditError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// An index file that is edited in place.
+///
+/// Only the lines that are added or changed are (re-)serialized. Every other line is written back
+/// out byte-for-byte as it was read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexFile {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    // Including the trailing newline, if any.
+    raw: String,
+    // `None` for blank lines.
+    version: Option<semver::Version>,
+}
+
+/// The fields of an entry, in the order they appear, with their values exactly as written.
+///
+/// This lets a single field be changed without dropping or normalizing any of the others,
+/// including fields that [`Entry`] does not know about.
+struct RawFields(Vec<(String, Box<RawValue>)>);
+
+impl<'de> Deserialize<'de> for RawFields {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = RawFields;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an index entry")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<RawFields, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(RawFields(fields))
+            }
+        }
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+impl Serialize for RawFields {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+/// Whether two versions are the same, ignoring build metadata (as cargo does).
+fn same_version(a: &semver::Version, b: &semver::Version) -> bool {
+    (a.major, a.minor, a.patch, &a.pre) == (b.major, b.minor, b.patch, &b.pre)
+}
+
+impl IndexFile {
+    /// Parses the contents of an existing index file.
+    ///
+    /// Unlike [`read_entries`], this fails if any line does not hold an entry, since a file that
+    /// isn't fully understood can't be safely edited.
+    pub fn parse(contents: &str) -> Result<Self, LineError> {
+        #[derive(Deserialize)]
+        struct Version {
+            vers: semver::Version,
+        }
+
+        let lines = contents
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, raw)| {
+                let version = if raw.trim().is_empty() {
+                    None
+                } else {
+                    let v: Version = serde_json::from_str(raw)
+                        .map_err(|error| LineError { line: i + 1, error })?;
+                    Some(v.vers)
+                };
+                Ok(Line {
+                    raw: raw.to_owned(),
+                    version,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { lines })
+    }
+
+    /// The versions in this file, in the order they appear.
+    pub fn versions(&self) -> impl Iterator<Item = &semver::Version> {
+        self.lines.iter().filter_map(|l| l.version.as_ref())
+    }
+
+    /// The line holding `version`, ignoring build metadata.
+    fn position(&self, version: &semver::Version) -> Option<usize> {
+        self.lines.iter().position(|l| {
+            l.version
+                .as_ref()
+                .map_or(false, |v| same_version(v, version))
+        })
+    }
+
+    /// Adds a new entry to the end of the file.
+    ///
+    /// Fails if the file already holds an entry with the same version. Versions that differ only
+    /// in their build metadata are considered the same.
+    pub fn append<Name, Req, Feature, Target, Links>(
+        &mut self,
+        entry: &Entry<Name, semver::Version, Req, Feature, Target, Links>,
+    ) -> Result<(), EditError>
+    where
+        Entry<Name, semver::Version, Req, Feature, Target, Links>: Serialize,
+        Feature: Ord,
+    {
+        let v = &entry.version;
+        if let Some(i) = self.position(v) {
+            let existing = self.lines[i]
+                .version
+                .clone()
+                .expect("position finds versions");
+            return Err(EditError::AlreadyExists(existing));
+        }
+
+        let mut raw = serde_json::to_string(entry)?;
+        raw.push('\n');
+        if let Some(last) = self.lines.last_mut() {
+            if !last.raw.ends_with('\n') {
+                last.raw.push('\n');
+            }
+        }
+        self.lines.push(Line {
+            raw,
+            version: Some(v.clone()),
+        });
+        Ok(())
+    }
+
+    /// Replaces the entry with the same version as `entry`.
+    ///
+    /// Versions that differ only in their build metadata are considered the same, and the build
+    /// metadata of `entry` is what ends up in the file.
+    pub fn replace<Name, Req, Feature, Target, Links>(
+        &mut self,
+        entry: &Entry<Name, semver::Version, Req, Feature, Target, Links>,
+    ) -> Result<(), EditError>
+    where
+        Entry<Name, semver::Version, Req, Feature, Target, Links>: Serialize,
+        Feature: Ord,
+    {
+        let i = self
+            .position(&entry.version)
+            .ok_or_else(|| EditError::NotFound(entry.version.clone()))?;
+        let mut raw = serde_json::to_string(entry)?;
+        raw.push('\n');
+        self.lines[i] = Line {
+            raw,
+            version: Some(entry.version.clone()),
+        };
+        Ok(())
+    }
+
+    /// Sets whether the entry with the given version is yanked.
+    ///
+    /// Versions that differ only in their build metadata are considered the same. Only the
+    /// `yanked` field is changed; every other field keeps its position and value, including those
+    /// that [`Entry`] does not know about. The line is left untouched if the entry is already in
+    /// the requested state.
+    pub fn set_yanked(&mut self, version: &semver::Version, yanked: bool) -> Result<(), EditError> {
+        let i = self
+            .position(version)
+            .ok_or_else(|| EditError::NotFound(version.clone()))?;
+        let line = &mut self.lines[i];
+        let mut fields: RawFields = serde_json::from_str(&line.raw)?;
+        let value = RawValue::from_string(yanked.to_string())?;
+        match fields.0.iter_mut().find(|(k, _)| k == "yanked") {
+            Some((_, v)) if serde_json::from_str::<bool>(v.get())? == yanked => return Ok(()),
+            Some((_, v)) => *v = value,
+            None if !yanked => return Ok(()),
+            None => fields.0.push(("yanked".to_string(), value)),
+        }
+        let newline = if line.raw.ends_with('\n') { "\n" } else { "" };
+        line.raw = serde_json::to_string(&fields)? + newline;
+        Ok(())
+    }
+}
+
+impl fmt::Display for IndexFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(&line.raw)?;
+        }
+        Ok(())
+    }
+}
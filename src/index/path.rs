@@ -0,0 +1,113 @@
+use std::fmt;
+
+/// The longest crate name a registry will accept.
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// The reason a string is not a legal registry crate name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// The name is empty.
+    Empty,
+    /// The name is longer than [`MAX_NAME_LENGTH`] characters.
+    TooLong,
+    /// The name does not start with an ASCII letter.
+    InvalidStart(char),
+    /// The name contains a character other than ASCII letters, digits, `-`, and `_`.
+    InvalidChar(char),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "crate name cannot be empty"),
+            Self::TooLong => write!(
+                f,
+                "crate name cannot be longer than {MAX_NAME_LENGTH} characters"
+            ),
+            Self::InvalidStart(c) => {
+                write!(f, "crate name must start with an ASCII letter, not `{c}`")
+            }
+            Self::InvalidChar(c) => write!(f, "invalid character `{c}` in crate name"),
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
+
+/// Checks that `name` is a legal crate name for a registry.
+///
+/// Registry names are more restricted than what cargo accepts for local packages: they must be
+/// ASCII, start with a letter, and consist only of letters, digits, `-`, and `_`.
+pub fn validate_name(name: &str) -> Result<(), NameError> {
+    let mut chars = name.chars();
+    match chars.next() {
+        None => return Err(NameError::Empty),
+        Some(c) if !c.is_ascii_alphabetic() => return Err(NameError::InvalidStart(c)),
+        Some(_) => {}
+    }
+    if let Some(c) = chars.find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+        return Err(NameError::InvalidChar(c));
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(NameError::TooLong);
+    }
+    Ok(())
+}
+
+/// The directory (relative to the index root) that holds the index file for `name`.
+///
+/// This is `1`, `2`, `3/{first-char}`, or `{first-two}/{second-two}` depending on the length of
+/// the name. The case of `name` is preserved, which matches the `{prefix}` marker in a registry's
+/// `dl` template. [`path_for`] lower-cases the name first.
+///
+/// Lengths are counted in characters, so this never panics, even for names that are not valid
+/// (see [`validate_name`]). An empty name has an empty prefix.
+pub fn prefix(name: &str) -> String {
+    // The byte offset at which the first `n` characters end.
+    let end = |n| name.char_indices().nth(n).map_or(name.len(), |(i, _)| i);
+    match name.chars().count() {
+        0 => String::new(),
+        1 => "1".to_string(),
+        2 => "2".to_string(),
+        3 => format!("3/{}", &name[..end(1)]),
+        _ => format!("{}/{}", &name[..end(2)], &name[end(2)..end(4)]),
+    }
+}
+
+/// The path (relative to the index root) of the index file for `name`.
+///
+/// Both git and sparse indices use the same layout, so this is the path of the file within a git
+/// index as well as the URL path relative to the root of a sparse index (see [`sparse_url`]). Index
+/// paths are always lower-case, since crate names are unique irrespective of case.
+pub fn path_for(name: &str) -> Result<String, NameError> {
+    validate_name(name)?;
+    let name = name.to_ascii_lowercase();
+    Ok(format!("{}/{}", prefix(&name), name))
+}
+
+/// The URL of the index file for `name` in the sparse index at `index`.
+///
+/// `index` may include the `sparse+` prefix that cargo uses to mark sparse registries.
+pub fn sparse_url(index: &str, name: &str) -> Result<String, NameError> {
+    let index = index.strip_prefix("sparse+").unwrap_or(index);
+    Ok(format!(
+        "{}/{}",
+        index.trim_end_matches('/'),
+        path_for(name)?
+    ))
+}
+
+/// The crate name whose index file lives at `path` (relative to the index root).
+///
+/// Returns `None` if `path` is not where [`path_for`] would place the file for that name, such as
+/// for the index's `config.json`.
+pub fn name_from_path(path: &str) -> Option<&str> {
+    let (dir, name) = path.rsplit_once('/')?;
+    if validate_name(name).is_err() || name.bytes().any(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    if dir != prefix(name) {
+        return None;
+    }
+    Some(name)
+}
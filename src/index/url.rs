@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The URL of the git index of crates.io.
+pub const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
+
+/// The URL of the sparse index of crates.io, including cargo's `sparse+` prefix.
+pub const CRATES_IO_SPARSE_INDEX: &str = "sparse+https://index.crates.io/";
+
+/// The index URL of a registry, as it appears in a manifest or in `Dependency::registry`.
+///
+/// Two `RegistryUrl`s are equal if they identify the same registry. Like cargo's canonical URLs:
+///
+///  - the scheme and host are compared case-insensitively,
+///  - trailing `/`s and a trailing `.git` are ignored, and
+///  - paths on `github.com` are compared case-insensitively.
+///
+/// A sparse index (with the `sparse+` prefix) and a git index at the same URL are different
+/// registries, as they are to cargo. The one exception is crates.io, whose git and sparse indexes
+/// are the same registry.
+///
+/// The URL as originally given is kept, and is what [`Display`](fmt::Display) writes.
+#[derive(Debug, Clone)]
+pub struct RegistryUrl {
+    raw: String,
+    canonical: String,
+}
+
+impl RegistryUrl {
+    /// Wraps the index URL `url`.
+    ///
+    /// The URL is not otherwise validated.
+    pub fn new(url: impl Into<String>) -> Self {
+        let raw = url.into();
+        let canonical = canonicalize(&raw);
+        Self { raw, canonical }
+    }
+
+    /// The index of crates.io.
+    pub fn crates_io() -> Self {
+        Self::new(CRATES_IO_INDEX)
+    }
+
+    /// The URL as originally given.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The canonical form of the URL that is used for comparisons.
+    ///
+    /// Both crates.io indexes canonicalize to (the canonical form of) [`CRATES_IO_INDEX`].
+    pub fn canonical(&self) -> &str {
+        &self.canonical
+    }
+
+    /// Whether the URL has cargo's `sparse+` prefix.
+    pub fn is_sparse(&self) -> bool {
+        self.raw.starts_with("sparse+")
+    }
+
+    /// Whether this is (either index of) crates.io.
+    pub fn is_crates_io(&self) -> bool {
+        self.canonical == CRATES_IO_INDEX
+    }
+}
+
+fn canonicalize(url: &str) -> String {
+    let (kind, url) = match url.strip_prefix("sparse+") {
+        Some(url) => ("sparse+", url),
+        None => ("", url),
+    };
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let scheme = scheme.to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+
+    let mut path = path.trim_end_matches('/');
+    // GitHub treats repository paths case-insensitively, and so does cargo.
+    let lower;
+    if host == "github.com" {
+        lower = path.to_ascii_lowercase();
+        path = &lower;
+    }
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let path = path.trim_end_matches('/');
+
+    let canonical = if scheme.is_empty() {
+        format!("{host}{path}")
+    } else {
+        format!("{scheme}://{host}{path}")
+    };
+    // The sparse index of crates.io is also accepted without its prefix.
+    if canonical == "https://index.crates.io" {
+        CRATES_IO_INDEX.to_string()
+    } else {
+        format!("{kind}{canonical}")
+    }
+}
+
+impl PartialEq for RegistryUrl {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for RegistryUrl {}
+
+impl PartialOrd for RegistryUrl {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RegistryUrl {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical.cmp(&other.canonical)
+    }
+}
+
+impl Hash for RegistryUrl {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+
+impl fmt::Display for RegistryUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl From<&str> for RegistryUrl {
+    fn from(url: &str) -> Self {
+        Self::new(url)
+    }
+}
+
+impl From<String> for RegistryUrl {
+    fn from(url: String) -> Self {
+        Self::new(url)
+    }
+}
@@ -0,0 +1,304 @@
+use super::Entry;
+use crate::dotcrate::NormalizedManifest;
+use crate::feature::{FeatureMap, FeatureTable, FeatureValue};
+use crate::publish::DependencyKind;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A semantic problem with an [`Entry`] or a [`NormalizedManifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A feature value refers to a feature that does not exist.
+    UnknownFeature {
+        /// The feature with the bad value.
+        feature: String,
+        /// The feature that does not exist.
+        missing: String,
+    },
+    /// A feature value refers to a dependency that does not exist.
+    UnknownDependency {
+        /// The feature with the bad value.
+        feature: String,
+        /// The dependency that does not exist.
+        missing: String,
+    },
+    /// A feature value enables a dependency that is not optional, like `dep:b`, `b?/f`, or just
+    /// `b`.
+    NotOptional {
+        /// The feature with the bad value.
+        feature: String,
+        /// The bad value.
+        value: String,
+    },
+    /// Features enable each other in a cycle.
+    ///
+    /// Holds the features in the cycle, starting with the smallest name.
+    FeatureCycle(Vec<String>),
+    /// More than one dependency has the same name, kind, and target.
+    DuplicateDependency {
+        /// The name of the dependency, as it is called in `Cargo.toml`.
+        name: String,
+        /// The kind of the dependencies.
+        kind: DependencyKind,
+        /// The target of the dependencies.
+        target: Option<String>,
+    },
+    /// The entry has `features2`, but a `schema_version` older than 2.
+    SchemaVersion(Option<u8>),
+    /// A feature in `features` (rather than `features2`) uses `dep:` or `?/`, which breaks cargo
+    /// versions before 1.19.
+    NewSyntaxInFeatures(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFeature { feature, missing } => write!(
+                f,
+                "feature `{feature}` includes `{missing}`, which is neither a dependency nor another feature"
+            ),
+            Self::UnknownDependency { feature, missing } => write!(
+                f,
+                "feature `{feature}` refers to `{missing}`, which is not a dependency"
+            ),
+            Self::NotOptional { feature, value } => write!(
+                f,
+                "feature `{feature}` includes `{value}`, but the dependency is not optional"
+            ),
+            Self::FeatureCycle(cycle) => {
+                write!(f, "cyclic feature dependency: ")?;
+                for feature in cycle {
+                    write!(f, "`{feature}` -> ")?;
+                }
+                write!(f, "`{}`", cycle[0])
+            }
+            Self::DuplicateDependency { name, kind, target } => {
+                let kind = match kind {
+                    DependencyKind::Normal => "normal",
+                    DependencyKind::Build => "build",
+                    DependencyKind::Dev => "dev",
+                };
+                write!(f, "dependency `{name}` is listed more than once as a {kind} dependency")?;
+                if let Some(target) = target {
+                    write!(f, " for target `{target}`")?;
+                }
+                Ok(())
+            }
+            Self::SchemaVersion(v) => write!(
+                f,
+                "entry has features2, but schema version {} (it must be at least 2)",
+                v.unwrap_or(1)
+            ),
+            Self::NewSyntaxInFeatures(feature) => write!(
+                f,
+                "feature `{feature}` uses `dep:` or `?/`, so it must be in features2"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// What the checks need to know about a dependency.
+struct Dep<'a> {
+    name: &'a str,
+    kind: DependencyKind,
+    target: Option<&'a str>,
+    optional: bool,
+}
+
+fn check_dependencies(deps: &[Dep<'_>], errors: &mut Vec<ValidationError>) {
+    let mut seen = BTreeSet::new();
+    let mut reported = BTreeSet::new();
+    for d in deps {
+        let key = (d.name, d.kind, d.target);
+        if !seen.insert(key) && reported.insert(key) {
+            errors.push(ValidationError::DuplicateDependency {
+                name: d.name.to_string(),
+                kind: d.kind,
+                target: d.target.map(String::from),
+            });
+        }
+    }
+}
+
+fn check_features(features: FeatureMap<'_>, deps: &[Dep<'_>], errors: &mut Vec<ValidationError>) {
+    // A dependency is optional if any of its declarations is.
+    let mut optional = BTreeMap::new();
+    for d in deps {
+        *optional.entry(d.name).or_insert(false) |= d.optional;
+    }
+    let table = FeatureTable::new(
+        features,
+        optional.iter().filter(|(_, o)| **o).map(|(n, _)| *n),
+    );
+
+    for (feature, values) in &table.features {
+        if table.implicit.contains(feature) {
+            continue;
+        }
+        for value in values {
+            let error = match *value {
+                FeatureValue::Feature(f) if table.features.contains_key(f) => continue,
+                // Unlike an optional dependency, this has no implicit feature to enable.
+                FeatureValue::Feature(f) if optional.get(f) == Some(&false) => {
+                    ValidationError::NotOptional {
+                        feature: feature.to_string(),
+                        value: value.to_string(),
+                    }
+                }
+                FeatureValue::Feature(f) => ValidationError::UnknownFeature {
+                    feature: feature.to_string(),
+                    missing: f.to_string(),
+                },
+                FeatureValue::Dep { dep_name } | FeatureValue::DepFeature { dep_name, .. } => {
+                    match optional.get(dep_name) {
+                        None => ValidationError::UnknownDependency {
+                            feature: feature.to_string(),
+                            missing: dep_name.to_string(),
+                        },
+                        Some(false) if value.requires_features2() => ValidationError::NotOptional {
+                            feature: feature.to_string(),
+                            value: value.to_string(),
+                        },
+                        Some(_) => continue,
+                    }
+                }
+            };
+            errors.push(error);
+        }
+    }
+
+    check_cycles(&table.features, errors);
+}
+
+fn check_cycles(features: &FeatureMap<'_>, errors: &mut Vec<ValidationError>) {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    let mut state = BTreeMap::new();
+    let mut cycles = BTreeSet::new();
+    for root in features.keys() {
+        if state.contains_key(root) {
+            continue;
+        }
+        // Depth-first, with an explicit stack of the features being visited and the index of the
+        // next value to look at in each, so that long chains do not overflow the call stack.
+        let mut path = vec![(*root, 0)];
+        state.insert(*root, State::Visiting);
+        while let Some((feature, i)) = path.last_mut() {
+            let feature = *feature;
+            let value = features[feature].get(*i);
+            *i += 1;
+            let next = match value {
+                Some(FeatureValue::Feature(next)) => *next,
+                Some(_) => continue,
+                None => {
+                    path.pop();
+                    state.insert(feature, State::Done);
+                    continue;
+                }
+            };
+            match state.get(next) {
+                Some(State::Done) => {}
+                Some(State::Visiting) => {
+                    let start = path.iter().position(|(f, _)| *f == next).expect("on path");
+                    let mut cycle: Vec<_> =
+                        path[start..].iter().map(|(f, _)| f.to_string()).collect();
+                    let min = (0..cycle.len())
+                        .min_by_key(|i| &cycle[*i])
+                        .expect("cycles are not empty");
+                    cycle.rotate_left(min);
+                    cycles.insert(cycle);
+                }
+                None if features.contains_key(next) => {
+                    state.insert(next, State::Visiting);
+                    path.push((next, 0));
+                }
+                None => {}
+            }
+        }
+    }
+    errors.extend(cycles.into_iter().map(ValidationError::FeatureCycle));
+}
+
+fn result(errors: Vec<ValidationError>) -> Result<(), Vec<ValidationError>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl<Name, Version, Req, Feature, Target, Links> Entry<Name, Version, Req, Feature, Target, Links>
+where
+    Name: AsRef<str>,
+    Feature: Ord + AsRef<str>,
+    Target: AsRef<str>,
+{
+    /// Checks that this entry makes sense, the way cargo would when loading it.
+    ///
+    /// This finds feature values that refer to features or dependencies that do not exist, values
+    /// that enable non-optional dependencies, feature cycles, dependencies that are
+    /// listed twice with the same kind and target, and `features2` that is inconsistent with
+    /// `schema_version`.
+    ///
+    /// Every problem is reported, not just the first one.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let deps: Vec<_> = self
+            .dependencies
+            .iter()
+            .map(|d| Dep {
+                name: d.name.as_ref(),
+                kind: d.kind.unwrap_or(DependencyKind::Normal),
+                target: d.target.as_deref().map(AsRef::as_ref),
+                optional: d.optional,
+            })
+            .collect();
+        check_dependencies(&deps, &mut errors);
+        check_features(self.feature_values(), &deps, &mut errors);
+
+        if self.features2.as_ref().map_or(false, |f| !f.is_empty())
+            && self.schema_version.unwrap_or(1) < 2
+        {
+            errors.push(ValidationError::SchemaVersion(self.schema_version));
+        }
+        errors.extend(
+            self.features_with_new_syntax()
+                .map(|f| ValidationError::NewSyntaxInFeatures(f.to_string())),
+        );
+
+        result(errors)
+    }
+}
+
+impl<Name, Feature> NormalizedManifest<Name, Feature>
+where
+    Feature: Ord + AsRef<str>,
+{
+    /// Checks that the features and dependencies of this manifest make sense.
+    ///
+    /// This is [`Entry::validate`] for a manifest; the problems that are specific to how the
+    /// index stores features are not checked.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let deps: Vec<_> = self
+            .dependency_tables()
+            .flat_map(|(target, kind, deps)| {
+                deps.iter().map(move |(name, d)| Dep {
+                    name,
+                    kind,
+                    target,
+                    optional: d.optional.unwrap_or(false),
+                })
+            })
+            .collect();
+        check_dependencies(&deps, &mut errors);
+        check_features(self.feature_values(), &deps, &mut errors);
+        result(errors)
+    }
+}
@@ -1,3 +1,5 @@
 pub mod dotcrate;
+pub mod feature;
 pub mod index;
+pub mod license;
 pub mod publish;
@@ -0,0 +1,319 @@
+//! SPDX license expressions, as found in the `license` field of a manifest.
+//!
+//! <https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/>
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A single license in an [`Expression`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct License {
+    /// The license identifier, like `MIT` or `LicenseRef-Proprietary`.
+    ///
+    /// Identifiers are not checked against the SPDX license list.
+    pub id: String,
+    /// Whether the identifier was followed by `+`, meaning "this version or any later version".
+    pub or_later: bool,
+    /// The exception given with `WITH`, if any.
+    pub exception: Option<String>,
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)?;
+        if self.or_later {
+            f.write_str("+")?;
+        }
+        if let Some(e) = &self.exception {
+            write!(f, " WITH {e}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A parsed SPDX license expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expression {
+    /// A single license.
+    License(License),
+    /// All of the sub-expressions apply.
+    And(Vec<Expression>),
+    /// Any one of the sub-expressions may be chosen.
+    Or(Vec<Expression>),
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::License(l) => write!(f, "{l}"),
+            Self::And(es) | Self::Or(es) => {
+                let op = if matches!(self, Self::And(_)) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                for (i, e) in es.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(op)?;
+                    }
+                    // AND binds more tightly than OR, so only OR needs parentheses within AND.
+                    // Nested expressions of the same kind come from explicit parentheses, so keep
+                    // those too.
+                    match (self, e) {
+                        (_, Self::License(_)) | (Self::Or(_), Self::And(_)) => write!(f, "{e}")?,
+                        _ => write!(f, "({e})")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// An error from parsing an [`Expression`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The expression ended where a license or `(` was expected.
+    UnexpectedEnd,
+    /// The given token was not expected at this point in the expression.
+    UnexpectedToken(String),
+    /// A license or exception identifier contains characters other than ASCII letters, digits,
+    /// `-`, `.`, and `:`.
+    InvalidIdentifier(String),
+    /// Parentheses are nested more deeply than the parser allows.
+    TooDeep,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of license expression"),
+            Self::UnexpectedToken(t) => write!(f, "unexpected `{t}` in license expression"),
+            Self::InvalidIdentifier(id) => write!(f, "invalid license identifier `{id}`"),
+            Self::TooDeep => write!(f, "license expression is nested too deeply"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Word(&'a str),
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => f.write_str("("),
+            Self::Close => f.write_str(")"),
+            Self::And => f.write_str("AND"),
+            Self::Or => f.write_str("OR"),
+            Self::With => f.write_str("WITH"),
+            Self::Word(w) => f.write_str(w),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = s;
+    loop {
+        rest = rest.trim_start();
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return tokens,
+        };
+        let len = match c {
+            '(' => {
+                tokens.push(Token::Open);
+                1
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                1
+            }
+            // crates.io still accepts the legacy `/` separator to mean OR.
+            '/' => {
+                tokens.push(Token::Or);
+                1
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '/'))
+                    .unwrap_or(rest.len());
+                tokens.push(match &rest[..len] {
+                    "AND" | "and" => Token::And,
+                    "OR" | "or" => Token::Or,
+                    "WITH" | "with" => Token::With,
+                    word => Token::Word(word),
+                });
+                len
+            }
+        };
+        rest = &rest[len..];
+    }
+}
+
+fn identifier(word: &str) -> Result<String, ParseError> {
+    if word.is_empty()
+        || !word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':'))
+    {
+        return Err(ParseError::InvalidIdentifier(word.to_string()));
+    }
+    Ok(word.to_string())
+}
+
+/// How deeply parentheses may nest.
+///
+/// Parsing, and everything else that walks an [`Expression`], recurses once per level.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token<'a>>>,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Expression, ParseError> {
+        let mut es = vec![self.and()?];
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            es.push(self.and()?);
+        }
+        Ok(if es.len() == 1 {
+            es.pop().expect("just checked")
+        } else {
+            Expression::Or(es)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expression, ParseError> {
+        let mut es = vec![self.with()?];
+        while self.tokens.next_if_eq(&Token::And).is_some() {
+            es.push(self.with()?);
+        }
+        Ok(if es.len() == 1 {
+            es.pop().expect("just checked")
+        } else {
+            Expression::And(es)
+        })
+    }
+
+    fn with(&mut self) -> Result<Expression, ParseError> {
+        match self.tokens.next() {
+            None => Err(ParseError::UnexpectedEnd),
+            Some(Token::Open) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(ParseError::TooDeep);
+                }
+                self.depth += 1;
+                let e = self.or()?;
+                self.depth -= 1;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(e),
+                    Some(t) => Err(ParseError::UnexpectedToken(t.to_string())),
+                    None => Err(ParseError::UnexpectedEnd),
+                }
+            }
+            Some(Token::Word(word)) => {
+                let (id, or_later) = match word.strip_suffix('+') {
+                    Some(id) => (id, true),
+                    None => (word, false),
+                };
+                let exception = if self.tokens.next_if_eq(&Token::With).is_some() {
+                    match self.tokens.next() {
+                        Some(Token::Word(e)) => Some(identifier(e)?),
+                        Some(t) => return Err(ParseError::UnexpectedToken(t.to_string())),
+                        None => return Err(ParseError::UnexpectedEnd),
+                    }
+                } else {
+                    None
+                };
+                Ok(Expression::License(License {
+                    id: identifier(id)?,
+                    or_later,
+                    exception,
+                }))
+            }
+            Some(t) => Err(ParseError::UnexpectedToken(t.to_string())),
+        }
+    }
+}
+
+impl Expression {
+    /// Parses an SPDX license expression.
+    ///
+    /// As with crates.io, `/` is accepted as a (legacy) alternative to `OR`, and operators may be
+    /// lower-case.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(s).into_iter().peekable(),
+            depth: 0,
+        };
+        let e = parser.or()?;
+        match parser.tokens.next() {
+            None => Ok(e),
+            Some(t) => Err(ParseError::UnexpectedToken(t.to_string())),
+        }
+    }
+
+    /// Every license mentioned in this expression.
+    pub fn licenses(&self) -> Vec<&License> {
+        match self {
+            Self::License(l) => vec![l],
+            Self::And(es) | Self::Or(es) => es.iter().flat_map(|e| e.licenses()).collect(),
+        }
+    }
+
+    /// Whether the terms of this expression can be met using only licenses that are `accepted`.
+    ///
+    /// For `AND`, every sub-expression must be acceptable; for `OR`, at least one must be.
+    pub fn evaluate(&self, accepted: &impl Fn(&License) -> bool) -> bool {
+        match self {
+            Self::License(l) => accepted(l),
+            Self::And(es) => es.iter().all(|e| e.evaluate(accepted)),
+            Self::Or(es) => es.iter().any(|e| e.evaluate(accepted)),
+        }
+    }
+
+    /// Whether the terms of this expression can be met under `policy`.
+    pub fn satisfies(&self, policy: &Policy) -> bool {
+        self.evaluate(&|l| policy.accepts(l))
+    }
+}
+
+/// Lists of license identifiers to accept and reject.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Licenses that are accepted.
+    pub allow: BTreeSet<String>,
+    /// Licenses that are rejected, even if they also appear in `allow`.
+    pub deny: BTreeSet<String>,
+    /// Whether licenses that appear in neither list are accepted.
+    pub accept_unlisted: bool,
+}
+
+impl Policy {
+    /// Whether `license` is accepted.
+    ///
+    /// A list entry matches a license if it is the license's full form (like
+    /// `GPL-2.0+ WITH Classpath-exception-2.0`) or its bare identifier (like `GPL-2.0`).
+    pub fn accepts(&self, license: &License) -> bool {
+        let full = license.to_string();
+        let listed = |list: &BTreeSet<String>| list.contains(&full) || list.contains(&license.id);
+        if listed(&self.deny) {
+            false
+        } else if listed(&self.allow) {
+            true
+        } else {
+            self.accept_unlisted
+        }
+    }
+}
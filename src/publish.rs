@@ -1,5 +1,20 @@
+use super::dotcrate::{NormalizedManifest, StringOrBool};
+use super::index::RegistryUrl;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+mod api;
+pub use api::{
+    ApiError, ErrorResponse, OkResponse, OwnersRequest, OwnersResponse, PublishResponse, Warnings,
+};
+
+mod entry;
+
+mod validate;
+pub use validate::{Policy, ValidationError};
+
+mod wire;
+pub use wire::{decode, encode, Frame, Tarball, WireError, WireLimits};
 
 /// Section in which this dependency was defined
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, PartialOrd, Ord, Eq, Hash)]
@@ -50,28 +65,108 @@ pub struct CrateVersion<'a> {
     #[serde(borrow)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub links: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<Cow<'a, str>>,
 
     #[serde(default)]
-    badges: BTreeMap<String, String>,
+    pub badges: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// How to decide which registry each dependency comes from when converting a manifest.
+///
+/// See [`CrateVersion::new_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionOptions {
+    /// The registry being published to.
+    ///
+    /// Dependencies from this registry do not have [`Dependency::registry`] set.
+    pub target_registry: RegistryUrl,
+    /// The registry that dependencies without a `registry-index` come from.
+    ///
+    /// This is crates.io unless source replacement is in effect.
+    pub default_registry: RegistryUrl,
+    /// Registries to treat as other registries, such as when a registry mirrors another.
+    ///
+    /// Rewrites are applied (once) before a dependency's registry is compared with
+    /// [`target_registry`](Self::target_registry), and the rewritten URL is what ends up in
+    /// [`Dependency::registry`].
+    pub registry_rewrites: BTreeMap<RegistryUrl, RegistryUrl>,
+}
+
+impl ConversionOptions {
+    /// Options for publishing to `target_registry` with dependencies from crates.io by default.
+    pub fn new(target_registry: impl Into<RegistryUrl>) -> Self {
+        Self {
+            target_registry: target_registry.into(),
+            default_registry: RegistryUrl::crates_io(),
+            registry_rewrites: BTreeMap::new(),
+        }
+    }
+
+    /// The registry a dependency with the given `registry-index` comes from, after rewrites.
+    pub fn source_registry(&self, registry_index: Option<&str>) -> RegistryUrl {
+        let url = match registry_index {
+            Some(r) => RegistryUrl::new(r),
+            None => self.default_registry.clone(),
+        };
+        self.rewrite_registry(url)
+    }
+
+    /// Applies [`registry_rewrites`](Self::registry_rewrites) to `url`.
+    pub(crate) fn rewrite_registry(&self, url: RegistryUrl) -> RegistryUrl {
+        match self.registry_rewrites.get(&url) {
+            Some(rewritten) => rewritten.clone(),
+            None => url,
+        }
+    }
+
+    /// Undoes [`registry_rewrites`](Self::registry_rewrites) for `url`.
+    ///
+    /// If several registries were rewritten to `url`, the first (in [`RegistryUrl`] order) is
+    /// returned.
+    pub(crate) fn original_registry(&self, url: RegistryUrl) -> RegistryUrl {
+        match self.registry_rewrites.iter().find(|(_, to)| **to == url) {
+            Some((from, _)) => from.clone(),
+            None => url,
+        }
+    }
 }
 
 impl<'a> CrateVersion<'a> {
+    /// Constructs the publish payload for `m`.
+    ///
+    /// The readme path and contents are passed in separately, since they depend on the contents
+    /// of the `.crate` file. See [`resolve_readme`].
+    ///
+    /// `is_for` is the index URL of the registry being published to. Dependencies from any other
+    /// registry get [`Dependency::registry`] set; registries are compared as [`RegistryUrl`]s.
+    /// Dependencies without a `registry-index` are taken to be from crates.io; use
+    /// [`new_with`](Self::new_with) to change that.
     pub fn new<Name, Feature: Ord>(
-        mut m: super::dotcrate::NormalizedManifest<Name, Feature>,
-        (readme, readme_contents): (Option<Cow<'a, str>>, Option<Cow<'a, str>>),
+        m: super::dotcrate::NormalizedManifest<Name, Feature>,
+        readme: (Option<Cow<'a, str>>, Option<Cow<'a, str>>),
         is_for: &'_ str,
     ) -> Self
     where
         Name: Into<Cow<'a, str>>,
         Feature: Into<Cow<'a, str>>,
     {
-        // let (readme, readme_contents) = match m.package.readme {
-        //     Some(StringOrBool::Bool(false)) => (None, None),
-        //     Some(StringOrBool::Bool(true)) => todo!("read README.md"),
-        //     Some(StringOrBool::String(path)) => todo!("read {path}"),
-        //     None => todo!("depends on file contents in .crate"),
-        // };
+        Self::new_with(m, readme, &ConversionOptions::new(is_for))
+    }
 
+    /// Constructs the publish payload for `m`, deciding dependency registries using `options`.
+    ///
+    /// See [`new`](Self::new).
+    pub fn new_with<Name, Feature>(
+        mut m: super::dotcrate::NormalizedManifest<Name, Feature>,
+        (readme, readme_contents): (Option<Cow<'a, str>>, Option<Cow<'a, str>>),
+        options: &ConversionOptions,
+    ) -> Self
+    where
+        Name: Into<Cow<'a, str>>,
+        Feature: Ord + Into<Cow<'a, str>>,
+    {
         let deps = m
             .take_dependencies()
             .map(|(name_in_toml, d, kind)| {
@@ -85,20 +180,11 @@ impl<'a> CrateVersion<'a> {
                         (None, n.into())
                     }
                 };
-                let is_from = match d.registry_index {
-                    Some(r) => {
-                        // not (necessarily) from crates.io
-                        Cow::Owned(r)
-                    }
-                    None => {
-                        // from crates.io
-                        Cow::Borrowed("https://github.com/rust-lang/crates.io-index")
-                    }
-                };
-                let target_registry_dependent_src_registry = if is_from == is_for {
+                let is_from = options.source_registry(d.registry_index.as_deref());
+                let target_registry_dependent_src_registry = if is_from == options.target_registry {
                     None
                 } else {
-                    Some(is_from)
+                    Some(Cow::Owned(is_from.to_string()))
                 };
                 Dependency {
                     optional: d.optional.unwrap_or(false),
@@ -115,6 +201,7 @@ impl<'a> CrateVersion<'a> {
                     kind,
                     registry: target_registry_dependent_src_registry,
                     explicit_name_in_toml: explicit_name,
+                    public: d.public,
                 }
             })
             .collect();
@@ -159,9 +246,104 @@ impl<'a> CrateVersion<'a> {
             license_file: m.package.license_file.map(Into::into),
             repository: m.package.repository.map(Into::into),
             links: m.package.links.map(Into::into),
-            badges: BTreeMap::new(),
+            rust_version: m.package.rust_version.map(Into::into),
+            badges: m.badges.unwrap_or_default(),
         }
     }
+
+    /// The features of this version, with each value parsed.
+    pub fn feature_values(&self) -> crate::feature::FeatureMap<'_> {
+        crate::feature::parse_tables([&self.features])
+    }
+
+    /// Parses the `license` field as an SPDX license expression, if there is one.
+    pub fn license_expression(
+        &self,
+    ) -> Option<Result<crate::license::Expression, crate::license::ParseError>> {
+        self.license
+            .as_deref()
+            .map(crate::license::Expression::parse)
+    }
+}
+
+/// An error from [`resolve_readme`].
+#[derive(Debug)]
+pub enum ReadmeError {
+    /// The readme named by the manifest does not exist.
+    Missing(String),
+    /// The readme is not valid UTF-8.
+    Encoding(String, std::str::Utf8Error),
+}
+
+impl fmt::Display for ReadmeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(path) => write!(f, "readme `{path}` does not exist"),
+            Self::Encoding(path, e) => write!(f, "readme `{path}` is not valid UTF-8: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadmeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Encoding(_, e) => Some(e),
+            Self::Missing(_) => None,
+        }
+    }
+}
+
+const DEFAULT_README_FILES: [&str; 3] = ["README.md", "README.txt", "README"];
+
+/// The path of the readme given `package.readme`, following cargo's rules.
+pub(crate) fn readme_path(
+    readme: Option<&StringOrBool>,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    match readme {
+        None => DEFAULT_README_FILES
+            .iter()
+            .find(|f| exists(f))
+            .map(|f| f.to_string()),
+        Some(StringOrBool::Bool(false)) => None,
+        Some(StringOrBool::Bool(true)) => Some(DEFAULT_README_FILES[0].to_string()),
+        Some(StringOrBool::String(path)) => {
+            Some(path.strip_prefix("./").unwrap_or(path).to_string())
+        }
+    }
+}
+
+/// Works out the readme path and contents of a package, as expected by [`CrateVersion::new`].
+///
+/// `file` gives the contents of the file at a path relative to the package root, such as
+/// [`DotCrate::file`](super::dotcrate::DotCrate::file). This applies the same rules as cargo does
+/// to `package.readme`:
+///
+/// - `false` means there is no readme;
+/// - `true` means the readme is `README.md`;
+/// - a string is the path of the readme;
+/// - if unset, the readme is the first of `README.md`, `README.txt`, and `README` that exists, if
+///   any.
+#[allow(clippy::type_complexity)]
+pub fn resolve_readme<'a, Name, Feature>(
+    m: &NormalizedManifest<Name, Feature>,
+    file: impl Fn(&str) -> Option<&'a [u8]>,
+) -> Result<(Option<Cow<'a, str>>, Option<Cow<'a, str>>), ReadmeError>
+where
+    Feature: Ord,
+{
+    let path = match readme_path(m.package.readme.as_ref(), |p| file(p).is_some()) {
+        Some(path) => path,
+        None => return Ok((None, None)),
+    };
+    let contents = match file(&path) {
+        Some(contents) => contents,
+        None => return Err(ReadmeError::Missing(path)),
+    };
+    match std::str::from_utf8(contents) {
+        Ok(contents) => Ok((Some(Cow::Owned(path)), Some(Cow::Borrowed(contents)))),
+        Err(e) => Err(ReadmeError::Encoding(path, e)),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -185,4 +367,7 @@ pub struct Dependency<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(borrow)]
     pub explicit_name_in_toml: Option<Cow<'a, str>>,
+    // not known to crates-io
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
 }
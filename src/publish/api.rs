@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The response to a successful `PUT /api/v1/crates/new`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PublishResponse<'a> {
+    #[serde(borrow)]
+    #[serde(default)]
+    pub warnings: Warnings<'a>,
+}
+
+/// Problems with a publish that were not severe enough to reject it.
+///
+/// Cargo prints these to the user after publishing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Warnings<'a> {
+    /// Categories that are not known to the registry and were ignored.
+    #[serde(borrow)]
+    #[serde(default)]
+    pub invalid_categories: Vec<Cow<'a, str>>,
+    /// Badges that are not known to the registry and were ignored.
+    #[serde(borrow)]
+    #[serde(default)]
+    pub invalid_badges: Vec<Cow<'a, str>>,
+    /// Any other warnings.
+    #[serde(borrow)]
+    #[serde(default)]
+    pub other: Vec<Cow<'a, str>>,
+}
+
+/// The body of any unsuccessful response from the registry web API.
+///
+/// Cargo shows each error's `detail` to the user.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ErrorResponse<'a> {
+    #[serde(borrow)]
+    pub errors: Vec<ApiError<'a>>,
+}
+
+impl<'a> ErrorResponse<'a> {
+    /// An error response with a single error.
+    pub fn new(detail: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            errors: vec![ApiError {
+                detail: detail.into(),
+            }],
+        }
+    }
+}
+
+/// A single error in an [`ErrorResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ApiError<'a> {
+    #[serde(borrow)]
+    pub detail: Cow<'a, str>,
+}
+
+/// The response to a successful `DELETE /api/v1/crates/{crate}/{version}/yank` or
+/// `PUT /api/v1/crates/{crate}/{version}/unyank`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct OkResponse {
+    pub ok: bool,
+}
+
+/// The body of a `PUT` or `DELETE` to `/api/v1/crates/{crate}/owners`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct OwnersRequest<'a> {
+    /// The logins of the users (or `github:org:team` teams) to add or remove.
+    #[serde(borrow)]
+    pub users: Vec<Cow<'a, str>>,
+}
+
+/// The response to a successful change to a crate's owners.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct OwnersResponse<'a> {
+    pub ok: bool,
+    /// A message for cargo to show the user, such as which invitations were sent.
+    #[serde(borrow)]
+    pub msg: Cow<'a, str>,
+}
@@ -0,0 +1,183 @@
+use super::{ConversionOptions, CrateVersion, Dependency, DependencyKind};
+use crate::dotcrate::{DotCrate, NormalizedManifest, ReadError};
+use crate::index::{Entry, RegistryDependency, RegistryUrl};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+impl<'a> CrateVersion<'a> {
+    /// Reconstructs the publish payload that [`Entry::from_publish`] turned into `entry`.
+    ///
+    /// `features` and `features2` are merged back together, dependency renames are turned back
+    /// into [`Dependency::explicit_name_in_toml`], and dependency registries are kept as they are
+    /// (that is, relative to the registry `entry` is in).
+    ///
+    /// The index does not hold the package's metadata (authors, description, readme, and so on),
+    /// so those fields are left empty. See [`with_metadata`](Self::with_metadata) and
+    /// [`with_metadata_from`](Self::with_metadata_from) for filling them in.
+    pub fn from_entry<Name, Feature, Target, Links>(
+        entry: &Entry<Name, semver::Version, semver::VersionReq, Feature, Target, Links>,
+    ) -> Self
+    where
+        Name: Clone + Into<Cow<'a, str>>,
+        Feature: Clone + Ord + Into<Cow<'a, str>>,
+        Target: Clone + Into<Cow<'a, str>>,
+        Links: Clone + Into<Cow<'a, str>>,
+    {
+        Self::from_entry_registries(entry, |r| r.map(|r| Cow::Owned(r.to_string())))
+    }
+
+    /// Like [`from_entry`](Self::from_entry), but for publishing `entry` to another registry.
+    ///
+    /// `indexed_with` are the options `entry` was produced with, and `publish_with` those of the
+    /// registry it is to be published to. Each dependency's registry is resolved against
+    /// `indexed_with` (undoing its [`registry_rewrites`](ConversionOptions::registry_rewrites)),
+    /// and then decided anew by `publish_with` as [`new_with`](Self::new_with) would.
+    ///
+    /// Undoing the rewrites is ambiguous if they are not one-to-one: if several registries are
+    /// rewritten to the same URL, or a registry is rewritten to one that dependencies also use
+    /// directly, every dependency on that URL is taken to come from the first registry (in
+    /// [`RegistryUrl`] order) that was rewritten to it.
+    pub fn from_entry_with<Name, Feature, Target, Links>(
+        entry: &Entry<Name, semver::Version, semver::VersionReq, Feature, Target, Links>,
+        indexed_with: &ConversionOptions,
+        publish_with: &ConversionOptions,
+    ) -> Self
+    where
+        Name: Clone + Into<Cow<'a, str>>,
+        Feature: Clone + Ord + Into<Cow<'a, str>>,
+        Target: Clone + Into<Cow<'a, str>>,
+        Links: Clone + Into<Cow<'a, str>>,
+    {
+        Self::from_entry_registries(entry, |r| {
+            let url = match r {
+                Some(r) => RegistryUrl::new(r),
+                None => indexed_with.target_registry.clone(),
+            };
+            let url = indexed_with.original_registry(url);
+            let url = publish_with.rewrite_registry(url);
+            if url == publish_with.target_registry {
+                None
+            } else {
+                Some(Cow::Owned(url.to_string()))
+            }
+        })
+    }
+
+    fn from_entry_registries<Name, Feature, Target, Links>(
+        entry: &Entry<Name, semver::Version, semver::VersionReq, Feature, Target, Links>,
+        registry: impl Fn(Option<&str>) -> Option<Cow<'a, str>>,
+    ) -> Self
+    where
+        Name: Clone + Into<Cow<'a, str>>,
+        Feature: Clone + Ord + Into<Cow<'a, str>>,
+        Target: Clone + Into<Cow<'a, str>>,
+        Links: Clone + Into<Cow<'a, str>>,
+    {
+        // A feature in both tables gets the values from both, as cargo does when it loads them.
+        let mut features: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (k, vs) in entry
+            .features
+            .iter()
+            .chain(entry.features2.iter().flat_map(|f| f.iter()))
+        {
+            features
+                .entry(k.clone().into())
+                .or_default()
+                .extend(vs.iter().cloned().map(Into::into));
+        }
+
+        Self {
+            name: entry.name.clone().into(),
+            version: entry.version.clone(),
+            dependencies: entry
+                .dependencies
+                .iter()
+                .map(|d| from_registry_dependency(d, &registry))
+                .collect(),
+            features,
+            authors: Vec::new(),
+            description: None,
+            documentation: None,
+            homepage: None,
+            readme: None,
+            readme_file: None,
+            keywords: Vec::new(),
+            categories: Vec::new(),
+            license: None,
+            license_file: None,
+            repository: None,
+            links: entry.links.clone().map(Into::into),
+            rust_version: entry.rust_version.as_deref().map(|r| r.to_string().into()),
+            badges: Default::default(),
+        }
+    }
+
+    /// Fills in the package metadata from `m`, leaving the name, version, dependencies, features,
+    /// `links`, and `rust_version` alone.
+    ///
+    /// `readme` is the readme path and contents, as for [`new`](Self::new).
+    pub fn with_metadata<Name, Feature>(
+        self,
+        m: NormalizedManifest<Name, Feature>,
+        readme: (Option<Cow<'a, str>>, Option<Cow<'a, str>>),
+    ) -> Self
+    where
+        Name: Into<Cow<'a, str>>,
+        Feature: Ord + Into<Cow<'a, str>>,
+    {
+        let meta = Self::new(m, readme, crate::index::CRATES_IO_INDEX);
+        Self {
+            authors: meta.authors,
+            description: meta.description,
+            documentation: meta.documentation,
+            homepage: meta.homepage,
+            readme: meta.readme,
+            readme_file: meta.readme_file,
+            keywords: meta.keywords,
+            categories: meta.categories,
+            license: meta.license,
+            license_file: meta.license_file,
+            repository: meta.repository,
+            badges: meta.badges,
+            ..self
+        }
+    }
+
+    /// Fills in the package metadata from the manifest and readme in `dotcrate`.
+    ///
+    /// See [`with_metadata`](Self::with_metadata).
+    pub fn with_metadata_from(self, dotcrate: &'a DotCrate) -> Result<Self, ReadError> {
+        let m: NormalizedManifest<String, String> = dotcrate.manifest()?;
+        let readme = super::resolve_readme(&m, |p| dotcrate.file(p)).map_err(ReadError::Readme)?;
+        Ok(self.with_metadata(m, readme))
+    }
+}
+
+fn from_registry_dependency<'a, Name, Feature, Target>(
+    d: &RegistryDependency<Name, semver::VersionReq, Feature, Target>,
+    registry: impl Fn(Option<&str>) -> Option<Cow<'a, str>>,
+) -> Dependency<'a>
+where
+    Name: Clone + Into<Cow<'a, str>>,
+    Feature: Clone + Into<Cow<'a, str>>,
+    Target: Clone + Into<Cow<'a, str>>,
+{
+    let (name, explicit_name_in_toml) = match &d.package {
+        // { package = name } in the index means `name` was called `d.name` in Cargo.toml.
+        Some(p) => ((**p).clone().into(), Some(d.name.clone().into())),
+        None => (d.name.clone().into(), None),
+    };
+    Dependency {
+        optional: d.optional,
+        default_features: d.default_features,
+        name,
+        features: d.features.iter().cloned().map(Into::into).collect(),
+        requirements: d.requirements.clone(),
+        target: d.target.as_deref().cloned().map(Into::into),
+        // Entries written before `kind` was recorded only had normal dependencies.
+        kind: d.kind.unwrap_or(DependencyKind::Normal),
+        registry: registry(d.registry.as_deref().map(|r| &**r)),
+        explicit_name_in_toml,
+        public: d.public,
+    }
+}
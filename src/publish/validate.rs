@@ -0,0 +1,265 @@
+use super::CrateVersion;
+use crate::index::{NameError, RegistryUrl};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Crate names that crates.io reserves, mostly for crates in the Rust distribution.
+const RESERVED_NAMES: &[&str] = &[
+    "alloc",
+    "arena",
+    "ast",
+    "builtins",
+    "collections",
+    "compiler-builtins",
+    "compiler-rt",
+    "compiletest",
+    "core",
+    "coretest",
+    "debug",
+    "driver",
+    "flate",
+    "fmt_macros",
+    "grammar",
+    "graphviz",
+    "macro",
+    "macros",
+    "proc_macro",
+    "rbml",
+    "rust-installer",
+    "rustbook",
+    "rustc",
+    "rustc_back",
+    "rustc_borrowck",
+    "rustc_driver",
+    "rustc_llvm",
+    "rustc_resolve",
+    "rustc_trans",
+    "rustc_typeck",
+    "rustdoc",
+    "rustllvm",
+    "rustuv",
+    "serialize",
+    "std",
+    "syntax",
+    "test",
+    "unicode",
+    // Names of special files on Windows.
+    "aux",
+    "com1",
+    "com2",
+    "com3",
+    "com4",
+    "com5",
+    "com6",
+    "com7",
+    "com8",
+    "com9",
+    "con",
+    "lpt1",
+    "lpt2",
+    "lpt3",
+    "lpt4",
+    "lpt5",
+    "lpt6",
+    "lpt7",
+    "lpt8",
+    "lpt9",
+    "nul",
+    "prn",
+];
+
+/// Crate names that are the same up to case and `-`/`_` are considered the same name.
+fn canonical_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('-', "_")
+}
+
+/// The rules checked by [`CrateVersion::validate`].
+///
+/// The [`Default`] follows crates.io, except that it knows no categories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// Names that may not be published.
+    ///
+    /// Names are compared ignoring case and treating `-` and `_` as equal.
+    pub reserved_names: BTreeSet<String>,
+    /// The most keywords a crate may have.
+    pub max_keywords: usize,
+    /// The longest a keyword may be.
+    pub max_keyword_length: usize,
+    /// The slugs of the categories crates may be in.
+    pub categories: BTreeSet<String>,
+    /// The most categories a crate may be in.
+    pub max_categories: usize,
+    /// The longest (in bytes) a description may be.
+    pub max_description_length: usize,
+    /// The longest (in bytes) a readme may be.
+    pub max_readme_length: usize,
+    /// Whether dependencies may have a `*` version requirement.
+    pub allow_wildcard_dependencies: bool,
+    /// The index URLs of the other registries that dependencies may come from.
+    ///
+    /// URLs are compared as [`RegistryUrl`]s, so different spellings of the same registry match.
+    pub allowed_registries: BTreeSet<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            reserved_names: RESERVED_NAMES.iter().map(|s| canonical_name(s)).collect(),
+            max_keywords: 5,
+            max_keyword_length: 20,
+            categories: BTreeSet::new(),
+            max_categories: 5,
+            max_description_length: 1000,
+            max_readme_length: 10 * 1024 * 1024,
+            allow_wildcard_dependencies: false,
+            allowed_registries: BTreeSet::new(),
+        }
+    }
+}
+
+/// A way in which a [`CrateVersion`] violates a [`Policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The crate name is not a legal registry crate name.
+    InvalidName(NameError),
+    /// The crate name is reserved.
+    ReservedName(String),
+    /// The crate has more than [`Policy::max_keywords`] keywords.
+    TooManyKeywords(usize),
+    /// A keyword is too long, or contains characters other than ASCII letters, digits, `_`, `-`,
+    /// and `+`, or does not start with a letter or digit.
+    InvalidKeyword(String),
+    /// The crate is in more than [`Policy::max_categories`] categories.
+    TooManyCategories(usize),
+    /// A category is not one of [`Policy::categories`].
+    ///
+    /// Note that crates.io ignores unknown categories and reports them as
+    /// [`Warnings::invalid_categories`](super::Warnings::invalid_categories) instead.
+    UnknownCategory(String),
+    /// The description is longer than [`Policy::max_description_length`].
+    DescriptionTooLong(usize),
+    /// The readme is longer than [`Policy::max_readme_length`].
+    ReadmeTooLong(usize),
+    /// The named dependency has a `*` version requirement.
+    WildcardDependency(String),
+    /// The named dependency comes from a registry not in [`Policy::allowed_registries`].
+    OtherRegistry {
+        /// The name of the dependency.
+        dependency: String,
+        /// The index URL of the registry it comes from.
+        registry: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName(e) => write!(f, "{e}"),
+            Self::ReservedName(name) => write!(f, "crate name `{name}` is reserved"),
+            Self::TooManyKeywords(n) => write!(f, "too many keywords ({n})"),
+            Self::InvalidKeyword(kw) => write!(f, "invalid keyword `{kw}`"),
+            Self::TooManyCategories(n) => write!(f, "too many categories ({n})"),
+            Self::UnknownCategory(c) => write!(f, "unknown category `{c}`"),
+            Self::DescriptionTooLong(n) => write!(f, "description is too long ({n} bytes)"),
+            Self::ReadmeTooLong(n) => write!(f, "readme is too long ({n} bytes)"),
+            Self::WildcardDependency(dep) => write!(
+                f,
+                "dependency `{dep}` has a wildcard (`*`) version requirement"
+            ),
+            Self::OtherRegistry {
+                dependency,
+                registry,
+            } => write!(
+                f,
+                "dependency `{dependency}` comes from another registry ({registry})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn valid_keyword(kw: &str, max_len: usize) -> bool {
+    let mut chars = kw.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphanumeric() => {}
+        _ => return false,
+    }
+    kw.len() <= max_len
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+')
+}
+
+impl CrateVersion<'_> {
+    /// Checks this publish against the rules of a registry.
+    ///
+    /// Every violation is reported, not just the first one.
+    pub fn validate(&self, policy: &Policy) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        if let Err(e) = crate::index::validate_name(&self.name) {
+            errors.push(ValidationError::InvalidName(e));
+        } else if policy
+            .reserved_names
+            .iter()
+            .any(|r| canonical_name(r) == canonical_name(&self.name))
+        {
+            errors.push(ValidationError::ReservedName(self.name.to_string()));
+        }
+
+        if self.keywords.len() > policy.max_keywords {
+            errors.push(ValidationError::TooManyKeywords(self.keywords.len()));
+        }
+        for kw in &self.keywords {
+            if !valid_keyword(kw, policy.max_keyword_length) {
+                errors.push(ValidationError::InvalidKeyword(kw.to_string()));
+            }
+        }
+
+        if self.categories.len() > policy.max_categories {
+            errors.push(ValidationError::TooManyCategories(self.categories.len()));
+        }
+        for c in &self.categories {
+            if !policy.categories.contains(&**c) {
+                errors.push(ValidationError::UnknownCategory(c.to_string()));
+            }
+        }
+
+        if let Some(d) = &self.description {
+            if d.len() > policy.max_description_length {
+                errors.push(ValidationError::DescriptionTooLong(d.len()));
+            }
+        }
+        if let Some(r) = &self.readme {
+            if r.len() > policy.max_readme_length {
+                errors.push(ValidationError::ReadmeTooLong(r.len()));
+            }
+        }
+
+        for d in &self.dependencies {
+            let name = d.explicit_name_in_toml.as_ref().unwrap_or(&d.name);
+            if !policy.allow_wildcard_dependencies && d.requirements == semver::VersionReq::STAR {
+                errors.push(ValidationError::WildcardDependency(name.to_string()));
+            }
+            if let Some(registry) = &d.registry {
+                let url = RegistryUrl::new(&**registry);
+                if !policy
+                    .allowed_registries
+                    .iter()
+                    .any(|r| RegistryUrl::new(&**r) == url)
+                {
+                    errors.push(ValidationError::OtherRegistry {
+                        dependency: name.to_string(),
+                        registry: registry.to_string(),
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use super::CrateVersion;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// A part of the body of a publish request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    /// The JSON-encoded [`CrateVersion`].
+    Metadata,
+    /// The `.crate` file.
+    Tarball,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Metadata => write!(f, "metadata"),
+            Self::Tarball => write!(f, "tarball"),
+        }
+    }
+}
+
+/// An error from encoding or decoding the body of a publish request.
+#[derive(Debug)]
+pub enum WireError {
+    /// The body could not be read or written.
+    Io(io::Error),
+    /// The body ended before the given frame (or its length) was complete.
+    Truncated(Frame),
+    /// The given frame is longer than permitted.
+    TooLarge {
+        /// The frame that is too long.
+        frame: Frame,
+        /// The length of the frame.
+        len: u64,
+        /// The maximum permitted length.
+        limit: u64,
+    },
+    /// The metadata could not be serialized.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Truncated(frame) => write!(f, "publish body ends in the middle of the {frame}"),
+            Self::TooLarge { frame, len, limit } => write!(
+                f,
+                "{frame} is {len} bytes long, which is more than the limit of {limit} bytes"
+            ),
+            Self::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for WireError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WireError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Writes the body of a `PUT /api/v1/crates/new` request.
+///
+/// The body is the length of the JSON-encoded `metadata` as a little-endian `u32`, followed by the
+/// JSON, followed by the length of `dotcrate` as a little-endian `u32`, followed by `dotcrate`.
+pub fn encode<W: Write>(
+    mut w: W,
+    metadata: &CrateVersion<'_>,
+    dotcrate: &[u8],
+) -> Result<W, WireError> {
+    let json = serde_json::to_vec(metadata).map_err(WireError::Json)?;
+    write_frame(&mut w, Frame::Metadata, &json)?;
+    write_frame(&mut w, Frame::Tarball, dotcrate)?;
+    Ok(w)
+}
+
+fn write_frame(w: &mut impl Write, frame: Frame, data: &[u8]) -> Result<(), WireError> {
+    let len = u32::try_from(data.len()).map_err(|_| WireError::TooLarge {
+        frame,
+        len: data.len() as u64,
+        limit: u32::MAX.into(),
+    })?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(data)?;
+    Ok(())
+}
+
+/// Limits on the body of a publish request checked by [`decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireLimits {
+    /// The longest permitted JSON metadata.
+    pub max_metadata: u32,
+    /// The longest permitted `.crate` file.
+    pub max_tarball: u32,
+}
+
+impl Default for WireLimits {
+    /// 10 MiB for each, which is the default upload limit of crates.io.
+    fn default() -> Self {
+        Self {
+            max_metadata: 10 * 1024 * 1024,
+            max_tarball: 10 * 1024 * 1024,
+        }
+    }
+}
+
+fn read_len(r: &mut impl Read, frame: Frame, limit: u32) -> Result<u32, WireError> {
+    let mut len = [0; 4];
+    r.read_exact(&mut len).map_err(|e| truncated(e, frame))?;
+    let len = u32::from_le_bytes(len);
+    if len > limit {
+        return Err(WireError::TooLarge {
+            frame,
+            len: len.into(),
+            limit: limit.into(),
+        });
+    }
+    Ok(len)
+}
+
+fn truncated(e: io::Error, frame: Frame) -> WireError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        WireError::Truncated(frame)
+    } else {
+        WireError::Io(e)
+    }
+}
+
+/// Reads the body of a `PUT /api/v1/crates/new` request.
+///
+/// Returns the JSON metadata, which can be deserialized into a [`CrateVersion`], and a reader for
+/// the `.crate` file that follows it. The `.crate` file is not read until the returned
+/// [`Tarball`] is, so it can be streamed elsewhere rather than held in memory.
+///
+/// Both lengths are checked against `limits` before any of the corresponding frame is read.
+pub fn decode<R: Read>(mut r: R, limits: WireLimits) -> Result<(Vec<u8>, Tarball<R>), WireError> {
+    let len = read_len(&mut r, Frame::Metadata, limits.max_metadata)?;
+    let mut json = vec![0; len as usize];
+    r.read_exact(&mut json)
+        .map_err(|e| truncated(e, Frame::Metadata))?;
+    let len = read_len(&mut r, Frame::Tarball, limits.max_tarball)?;
+    Ok((
+        json,
+        Tarball {
+            inner: r,
+            len,
+            remaining: len.into(),
+        },
+    ))
+}
+
+/// The `.crate` file in the body of a publish request.
+///
+/// Reads fail with [`io::ErrorKind::UnexpectedEof`] if the body ends before the length given in
+/// the request.
+#[derive(Debug)]
+pub struct Tarball<R> {
+    inner: R,
+    len: u32,
+    remaining: u64,
+}
+
+impl<R> Tarball<R> {
+    /// The length of the `.crate` file as given in the request.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Whether the `.crate` file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Fails with [`WireError::Truncated`] if the `.crate` file has not been read in full.
+    pub fn finish(self) -> Result<R, WireError> {
+        if self.remaining != 0 {
+            return Err(WireError::Truncated(Frame::Tarball));
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<R: Read> Read for Tarball<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let max = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "publish body ends {} bytes into a {} byte tarball",
+                    u64::from(self.len) - self.remaining,
+                    self.len
+                ),
+            ));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,376 @@
+use cargo_index_transit::{dotcrate, index};
+use flate2::write::GzEncoder;
+use std::io::Write;
+
+const MANIFEST: &str = r#"
+[package]
+name = "foo"
+version = "0.1.0"
+readme = "README.md"
+
+[dependencies.bar]
+version = "1"
+"#;
+
+fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut ar = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        ar.append_data(&mut header, path, *contents).unwrap();
+    }
+    ar.into_inner().unwrap().finish().unwrap()
+}
+
+#[test]
+fn read() {
+    let bytes = tarball(&[
+        ("foo-0.1.0/Cargo.toml", MANIFEST.as_bytes()),
+        ("foo-0.1.0/Cargo.toml.orig", b"[package]\nname = \"foo\""),
+        ("foo-0.1.0/README.md", b"# foo"),
+        ("foo-0.1.0/src/lib.rs", b""),
+    ]);
+    let krate = dotcrate::DotCrate::from_bytes(&bytes).unwrap();
+    assert_eq!(krate.prefix(), "foo-0.1.0");
+    assert_eq!(krate.checksum(), dotcrate::checksum(&bytes));
+    assert_eq!(krate.manifest_str(), MANIFEST);
+    assert_eq!(krate.file("src/lib.rs"), Some(&b""[..]));
+    assert_eq!(krate.file("Cargo.toml"), None);
+    assert_eq!(
+        krate.original_manifest(),
+        Some(&b"[package]\nname = \"foo\""[..])
+    );
+    assert_eq!(krate.vcs_info(), None);
+    assert_eq!(krate.readme().unwrap(), Some(("README.md", &b"# foo"[..])));
+    assert_eq!(
+        krate.files().map(|(p, _)| p).collect::<Vec<_>>(),
+        ["Cargo.toml.orig", "README.md", "src/lib.rs"]
+    );
+
+    let entry = index::Entry::from_manifest(
+        krate.manifest::<String, String>().unwrap(),
+        "https://github.com/rust-lang/crates.io-index",
+        krate.checksum(),
+    );
+    assert_eq!(entry.name, "foo");
+    assert_eq!(entry.dependencies[0].name, "bar");
+    assert_eq!(entry.checksum, dotcrate::checksum(&bytes));
+}
+
+#[test]
+fn read_malformed() {
+    let bytes = tarball(&[("foo-0.1.0/src/lib.rs", b"")]);
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes(&bytes),
+        Err(dotcrate::ReadError::MissingManifest)
+    ));
+
+    let bytes = tarball(&[
+        ("foo-0.1.0/Cargo.toml", MANIFEST.as_bytes()),
+        ("bar-0.1.0/src/lib.rs", b""),
+    ]);
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes(&bytes),
+        Err(dotcrate::ReadError::OutsidePrefix(p)) if p == "bar-0.1.0/src/lib.rs"
+    ));
+
+    let bytes = tarball(&[("foo-0.2.0/Cargo.toml", MANIFEST.as_bytes())]);
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes(&bytes),
+        Err(dotcrate::ReadError::PrefixMismatch { prefix, expected })
+            if prefix == "foo-0.2.0" && expected == "foo-0.1.0"
+    ));
+
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes(b"not a tarball"),
+        Err(dotcrate::ReadError::Io(_))
+    ));
+
+    // A header that claims a huge file must not make the reader allocate that much.
+    let mut header = tar::Header::new_gnu();
+    header.set_path("foo-0.1.0/Cargo.toml").unwrap();
+    header.set_size(1 << 40);
+    header.set_mode(0o644);
+    header.set_cksum();
+    let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(header.as_bytes()).unwrap();
+    gz.write_all(MANIFEST.as_bytes()).unwrap();
+    let bytes = gz.finish().unwrap();
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes(&bytes),
+        Err(dotcrate::ReadError::Io(_))
+    ));
+
+    let bytes = tarball(&[
+        ("foo-0.1.0/Cargo.toml", MANIFEST.as_bytes()),
+        ("foo-0.1.0/big", &[0; 64 * 1024]),
+    ]);
+    assert!(matches!(
+        dotcrate::DotCrate::from_bytes_with(
+            &bytes,
+            dotcrate::Limits {
+                max_unpacked_size: 32 * 1024,
+            }
+        ),
+        Err(dotcrate::ReadError::TooLarge { limit: 32768 })
+    ));
+}
+
+#[test]
+fn write() {
+    let m: dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(MANIFEST).unwrap();
+    let mut builder = dotcrate::Builder::new();
+    builder
+        .file("src/lib.rs", "pub fn foo() {}")
+        .file("README.md", "# foo")
+        .file("src-extra/x.rs", "")
+        .executable("build.sh", "#!/bin/sh")
+        .file("Cargo.toml", "ignored");
+    let bytes = builder.build(&m).unwrap();
+    assert_eq!(bytes, builder.build(&m).unwrap());
+
+    let mut ar = tar::Archive::new(flate2::read::GzDecoder::new(&*bytes));
+    let headers: Vec<_> = ar
+        .entries()
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            let h = e.header();
+            (
+                e.path().unwrap().display().to_string(),
+                h.mode().unwrap(),
+                h.mtime().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            ("foo-0.1.0/Cargo.toml".to_string(), 0o644, 1),
+            ("foo-0.1.0/README.md".to_string(), 0o644, 1153704088),
+            ("foo-0.1.0/build.sh".to_string(), 0o755, 1153704088),
+            ("foo-0.1.0/src/lib.rs".to_string(), 0o644, 1153704088),
+            ("foo-0.1.0/src-extra/x.rs".to_string(), 0o644, 1153704088),
+        ]
+    );
+
+    let krate = dotcrate::DotCrate::from_bytes(&bytes).unwrap();
+    assert_eq!(krate.file("src/lib.rs"), Some(&b"pub fn foo() {}"[..]));
+    let entry = index::Entry::from_manifest(
+        krate.manifest::<String, String>().unwrap(),
+        "https://github.com/rust-lang/crates.io-index",
+        krate.checksum(),
+    );
+    assert_eq!(entry.checksum, dotcrate::checksum(&bytes));
+    assert_eq!(entry.dependencies[0].name, "bar");
+
+    let mut builder = dotcrate::Builder::new();
+    builder.file("../escape", "");
+    assert!(matches!(
+        builder.build(&m),
+        Err(dotcrate::WriteError::InvalidPath(p)) if p == "../escape"
+    ));
+}
+
+/// Like `tarball`, but without the path checks `tar::Builder` does.
+fn unchecked_tarball(entries: &[(&str, tar::EntryType, &str, &[u8])]) -> Vec<u8> {
+    let mut ar = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (path, kind, link, contents) in entries {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(*kind);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        ar.append(&header, *contents).unwrap();
+    }
+    ar.into_inner().unwrap().finish().unwrap()
+}
+
+#[test]
+fn validate() {
+    use tar::EntryType;
+    let version = semver::Version::new(0, 1, 0);
+
+    let bytes = unchecked_tarball(&[
+        ("foo-0.1.0/", EntryType::Directory, "", b""),
+        ("foo-0.1.0/Cargo.toml", EntryType::Regular, "", b"[package]"),
+        ("foo-0.1.0/src/lib.rs", EntryType::Regular, "", b""),
+        ("foo-0.1.0/src/alias.rs", EntryType::Symlink, "lib.rs", b""),
+        // Links to the package directory itself stay inside it.
+        ("foo-0.1.0/here", EntryType::Symlink, ".", b""),
+        ("foo-0.1.0/src/root", EntryType::Symlink, "..", b""),
+        (
+            "foo-0.1.0/hard.rs",
+            EntryType::Link,
+            "foo-0.1.0/src/lib.rs",
+            b"",
+        ),
+    ]);
+    dotcrate::validate(&*bytes, "foo", &version, dotcrate::Limits::default()).unwrap();
+
+    let bytes = unchecked_tarball(&[
+        ("foo-0.1.0/Cargo.toml", EntryType::Regular, "", b"[package]"),
+        ("/etc/passwd", EntryType::Regular, "", b""),
+        ("foo-0.1.0/../../x", EntryType::Regular, "", b""),
+        ("bar-0.1.0/Cargo.toml", EntryType::Regular, "", b""),
+        ("foo-0.1.0/up", EntryType::Symlink, "../..", b""),
+        ("foo-0.1.0/abs", EntryType::Symlink, "/etc/passwd", b""),
+        (
+            "foo-0.1.0/hard",
+            EntryType::Link,
+            "bar-0.1.0/Cargo.toml",
+            b"",
+        ),
+        ("foo-0.1.0/fifo", EntryType::Fifo, "", b""),
+        ("foo-0.1.0/cargo.toml", EntryType::Regular, "", b""),
+    ]);
+    let errors = dotcrate::validate(&*bytes, "foo", &version, dotcrate::Limits::default())
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "`/etc/passwd` is an absolute path",
+            "`foo-0.1.0/../../x` contains `..`",
+            "`bar-0.1.0/Cargo.toml` is outside the package directory",
+            "link `foo-0.1.0/up` points to `../..`, which is outside the package directory",
+            "link `foo-0.1.0/abs` points to `/etc/passwd`, which is outside the package directory",
+            "link `foo-0.1.0/hard` points to `bar-0.1.0/Cargo.toml`, which is outside the package directory",
+            "`foo-0.1.0/fifo` has unsupported entry type Fifo",
+            "`foo-0.1.0/Cargo.toml` and `foo-0.1.0/cargo.toml` differ only in case",
+        ]
+    );
+
+    // Each link stays inside on its own, but `A` goes through `B`.
+    let bytes = unchecked_tarball(&[
+        ("foo-0.1.0/Cargo.toml", EntryType::Regular, "", b"[package]"),
+        ("foo-0.1.0/x/y/z/B", EntryType::Symlink, "../..", b""),
+        ("foo-0.1.0/x/y/z/A", EntryType::Symlink, "B/../../..", b""),
+        ("foo-0.1.0/x/y/z/C", EntryType::Symlink, "B/y/z/B", b""),
+    ]);
+    let errors = dotcrate::validate(&*bytes, "foo", &version, dotcrate::Limits::default())
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        ["link `foo-0.1.0/x/y/z/A` points to `B/../../..`, which is outside the package directory"]
+    );
+
+    let bytes = tarball(&[("foo-0.1.0/big", &[0; 64 * 1024])]);
+    let errors = dotcrate::validate(
+        &*bytes,
+        "foo",
+        &version,
+        dotcrate::Limits {
+            max_unpacked_size: 32 * 1024,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        &errors[..],
+        [dotcrate::ValidationError::TooLarge { limit: 32768 }]
+    ));
+}
+
+#[test]
+fn normalized_toml() {
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = ["Jane"]
+        license = "MIT"
+        description = "A foo"
+        edition = "2021"
+        links = "foo"
+        rust-version = "1.60"
+
+        [dependencies]
+        bar = { version = "1", optional = true, features = ["x"], default-features = false }
+        baz = { version = "0.2", package = "qux", registry-index = "https://example.com/index" }
+
+        [dev-dependencies.quux]
+        version = "1"
+
+        [features]
+        default = ["bar"]
+
+        [target."cfg(unix)".dependencies.libc]
+        version = "0.2"
+
+        [badges]
+        maintenance = { status = "passively-maintained" }
+        "#,
+    )
+    .unwrap();
+    let toml = m.to_toml().unwrap();
+    assert_eq!(
+        toml,
+        r#"# THIS FILE IS AUTOMATICALLY GENERATED BY CARGO
+#
+# When uploading crates to the registry Cargo will automatically
+# "normalize" Cargo.toml files for maximal compatibility
+# with all versions of Cargo and also rewrite `path` dependencies
+# to registry (e.g., crates.io) dependencies.
+#
+# If you are reading this file be aware that the original Cargo.toml
+# will likely look very different (and much more reasonable).
+# See Cargo.toml.orig for the original contents.
+
+[package]
+edition = "2021"
+rust-version = "1.60"
+name = "foo"
+version = "0.1.0"
+authors = ["Jane"]
+links = "foo"
+description = "A foo"
+license = "MIT"
+
+[dependencies.bar]
+version = "^1"
+features = ["x"]
+optional = true
+default-features = false
+
+[dependencies.baz]
+version = "^0.2"
+registry-index = "https://example.com/index"
+package = "qux"
+
+[dev-dependencies.quux]
+version = "^1"
+
+[features]
+default = ["bar"]
+
+[target."cfg(unix)".dependencies.libc]
+version = "^0.2"
+
+[badges.maintenance]
+status = "passively-maintained"
+"#
+    );
+
+    // Writing is stable across a round-trip through the written file.
+    let m2: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(&toml).unwrap();
+    assert_eq!(m2.to_toml().unwrap(), toml);
+
+    // And it is what ends up in a .crate file.
+    let bytes = dotcrate::Builder::new()
+        .file("src/lib.rs", "")
+        .build(&m)
+        .unwrap();
+    let krate = dotcrate::DotCrate::from_bytes(&bytes).unwrap();
+    assert_eq!(krate.manifest_str(), toml);
+}
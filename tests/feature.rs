@@ -0,0 +1,238 @@
+use cargo_index_transit::{dotcrate, feature::FeatureValue, index, publish};
+use proptest::prelude::*;
+use serde_json::json;
+
+mod util;
+
+#[test]
+fn parse() {
+    for (s, v) in [
+        ("std", FeatureValue::Feature("std")),
+        ("dep:serde", FeatureValue::Dep { dep_name: "serde" }),
+        (
+            "serde/derive",
+            FeatureValue::DepFeature {
+                dep_name: "serde",
+                dep_feature: "derive",
+                weak: false,
+            },
+        ),
+        (
+            "serde?/derive",
+            FeatureValue::DepFeature {
+                dep_name: "serde",
+                dep_feature: "derive",
+                weak: true,
+            },
+        ),
+    ] {
+        assert_eq!(FeatureValue::new(s), v);
+        assert_eq!(v.to_string(), s);
+    }
+    assert!(!FeatureValue::new("std").requires_features2());
+    assert!(!FeatureValue::new("serde/derive").requires_features2());
+    assert!(FeatureValue::new("dep:serde").requires_features2());
+    assert!(FeatureValue::new("serde?/derive").requires_features2());
+    // Malformed values are split the way crates.io splits them.
+    assert!(FeatureValue::new("dep:serde/derive").requires_features2());
+    assert!(FeatureValue::new("serde/derive?/x").requires_features2());
+}
+
+proptest! {
+    #[test]
+    fn requires_features2_like_crates_io(s in "(dep:)?[a-z?/]{0,8}") {
+        prop_assert_eq!(
+            FeatureValue::new(&s).requires_features2(),
+            s.starts_with("dep:") || s.contains("?/")
+        );
+    }
+
+    #[test]
+    fn display_roundtrips(s in "(dep:)?[a-z?/]{0,8}") {
+        prop_assert_eq!(FeatureValue::new(&s).to_string(), s);
+    }
+}
+
+#[test]
+fn feature_values() {
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+
+        [dependencies.serde]
+        version = "1"
+        optional = true
+
+        [features]
+        default = ["std"]
+        std = []
+        derive = ["dep:serde", "serde?/derive"]
+        "#,
+    )
+    .unwrap();
+    let expected = [
+        ("default", vec![FeatureValue::Feature("std")]),
+        (
+            "derive",
+            vec![
+                FeatureValue::Dep { dep_name: "serde" },
+                FeatureValue::DepFeature {
+                    dep_name: "serde",
+                    dep_feature: "derive",
+                    weak: true,
+                },
+            ],
+        ),
+        ("std", vec![]),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(m.feature_values(), expected);
+
+    let cv = publish::CrateVersion::new(m.clone(), (None, None), index::CRATES_IO_INDEX);
+    assert_eq!(cv.feature_values(), expected);
+
+    let entry = index::Entry::from_manifest(m, index::CRATES_IO_INDEX, [0; 32]);
+    assert_eq!(entry.features.len(), 2);
+    assert_eq!(entry.features2.as_ref().unwrap().len(), 1);
+    assert_eq!(entry.feature_values(), expected);
+}
+
+#[test]
+fn resolve() {
+    use cargo_index_transit::feature::{DependencyFeatures, ResolveError};
+
+    let e = util::entry(
+        &[
+            util::dep("core", json!({"features": ["alloc"]})),
+            util::dep("serde", json!({"optional": true})),
+            util::dep("rand", json!({"optional": true})),
+            util::dep("log", json!({"optional": true})),
+        ],
+        json!({"default": ["std"], "std": ["core/std"], "tls": ["rand/std"]}),
+        json!({"features2": {"derive": ["dep:serde", "serde/derive"], "logging": ["log?/kv"]}}),
+    );
+
+    let r = e.resolve_features([], true).unwrap();
+    assert_eq!(r.features, ["default", "std"].into());
+    assert!(r.optional_dependencies.is_empty());
+    assert_eq!(
+        r.dependencies,
+        [(
+            "core",
+            DependencyFeatures {
+                features: ["alloc", "std"].into(),
+                default_features: true,
+            }
+        )]
+        .into()
+    );
+
+    // `rand/std` enables `rand` and its implicit feature.
+    let r = e.resolve_features(["tls"], false).unwrap();
+    assert_eq!(r.features, ["rand", "tls"].into());
+    assert_eq!(r.optional_dependencies, ["rand"].into());
+    assert_eq!(r.dependencies["rand"].features, ["std"].into());
+    assert_eq!(r.dependencies["core"].features, ["alloc"].into());
+
+    // `serde` has no implicit feature, since `dep:serde` hides it.
+    let r = e.resolve_features(["derive"], false).unwrap();
+    assert_eq!(r.features, ["derive"].into());
+    assert_eq!(r.optional_dependencies, ["serde"].into());
+    assert_eq!(r.dependencies["serde"].features, ["derive"].into());
+    assert_eq!(
+        e.resolve_features(["serde"], false),
+        Err(ResolveError::UnknownFeature("serde".into()))
+    );
+
+    // Weak features only apply if the dependency is enabled some other way.
+    let r = e.resolve_features(["logging"], false).unwrap();
+    assert!(r.optional_dependencies.is_empty());
+    assert!(!r.dependencies.contains_key("log"));
+    let r = e.resolve_features(["logging", "log"], false).unwrap();
+    assert_eq!(r.optional_dependencies, ["log"].into());
+    assert_eq!(r.dependencies["log"].features, ["kv"].into());
+
+    // Values can be requested directly.
+    let r = e.resolve_features(["log/kv"], false).unwrap();
+    assert_eq!(r.features, ["log"].into());
+    assert_eq!(r.dependencies["log"].features, ["kv"].into());
+
+    assert_eq!(
+        e.resolve_features(["nope"], false),
+        Err(ResolveError::UnknownFeature("nope".into()))
+    );
+    assert_eq!(
+        e.resolve_features(["nope/x"], false),
+        Err(ResolveError::UnknownDependency("nope".into()))
+    );
+
+    // Long chains of features resolve without recursing once per link.
+    let chain: serde_json::Map<_, _> = (0..100_000)
+        .map(|i| (format!("f{i}"), json!([format!("f{}", i + 1)])))
+        .chain([("f100000".to_string(), json!([]))])
+        .collect();
+    let e = util::entry(&[], chain.into(), json!({}));
+    let r = e.resolve_features(["f0"], false).unwrap();
+    assert_eq!(r.features.len(), 100_001);
+}
+
+#[test]
+fn effective_features() {
+    let deps = [
+        util::dep("serde", json!({"optional": true})),
+        util::dep("rand", json!({"optional": true})),
+        util::dep("log", json!({"optional": true})),
+        util::dep("core", json!({})),
+    ];
+    let e = util::entry(
+        &deps,
+        json!({"default": ["rand"], "log": ["log/std"]}),
+        json!({"features2": {"derive": ["dep:serde"]}}),
+    );
+    let table = e.effective_features();
+    assert_eq!(table.implicit, ["rand"].into());
+    assert_eq!(
+        table.features["rand"],
+        [FeatureValue::Dep { dep_name: "rand" }]
+    );
+    assert!(!table.features.contains_key("serde"));
+    assert!(!table.features.contains_key("core"));
+    assert_eq!(table.explicit(), e.feature_values());
+
+    let migrated = table.migrated();
+    assert_eq!(
+        migrated,
+        [
+            ("default", vec!["rand"]),
+            ("derive", vec!["dep:serde"]),
+            ("log", vec!["log/std"]),
+            ("rand", vec!["dep:rand"]),
+        ]
+        .into_iter()
+        .map(|(k, vs)| (k.to_string(), vs.into_iter().map(String::from).collect()))
+        .collect()
+    );
+
+    // Writing out the migrated table leaves the features unchanged, but nothing is implicit.
+    let e2 = util::entry(&deps, json!({}), json!({"features2": migrated}));
+    let table2 = e2.effective_features();
+    assert!(table2.implicit.is_empty());
+    assert_eq!(table2.features, table.features);
+
+    // A feature in both tables gets the values from both.
+    let e3 = util::entry(
+        &deps,
+        json!({"log": ["log/std"]}),
+        json!({"features2": {"log": ["dep:log"]}}),
+    );
+    assert_eq!(
+        e3.feature_values()["log"],
+        [
+            FeatureValue::new("log/std"),
+            FeatureValue::Dep { dep_name: "log" }
+        ]
+    );
+}
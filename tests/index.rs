@@ -0,0 +1,516 @@
+use cargo_index_transit::{index, publish::DependencyKind};
+use serde_json::json;
+use std::sync::Arc;
+
+mod util;
+
+type Entry = index::Entry<String, semver::Version, semver::VersionReq, String, String, String>;
+
+const FILE: &str = r#"{"name":"foo","vers":"0.1.0","deps":[],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{},"yanked":false}
+{"name":"foo","vers":"0.2.0","deps":[{"name":"bar","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"1111111111111111111111111111111111111111111111111111111111111111","features":{"default":["bar"]},"yanked":false}
+{"name":"foo","vers":"0.2.1","deps":[{"name":"bar","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"2222222222222222222222222222222222222222222222222222222222222222","features":{"default":["bar"]},"yanked":true}
+{"name":"foo","vers":"not a version"}
+
+{"name":"foo","vers":"0.3.0","deps":[],"cksum":"3333333333333333333333333333333333333333333333333333333333333333","features":{},"yanked":false}
+"#;
+
+#[test]
+fn read_entries() {
+    let entries: Vec<Result<Entry, _>> = index::read_entries(FILE).collect();
+    assert_eq!(entries.len(), 5);
+
+    let err = entries[3].as_ref().unwrap_err();
+    assert_eq!(err.line(), 4);
+
+    let entries: Vec<_> = entries.into_iter().filter_map(Result::ok).collect();
+    let versions: Vec<_> = entries.iter().map(|e| e.version.to_string()).collect();
+    assert_eq!(versions, ["0.1.0", "0.2.0", "0.2.1", "0.3.0"]);
+    assert!(entries[2].yanked);
+
+    // Identical lists should share an allocation.
+    assert!(Arc::ptr_eq(
+        &entries[1].dependencies,
+        &entries[2].dependencies
+    ));
+    assert!(Arc::ptr_eq(&entries[1].features, &entries[2].features));
+    assert!(Arc::ptr_eq(
+        &entries[0].dependencies,
+        &entries[3].dependencies
+    ));
+    assert!(Arc::ptr_eq(&entries[0].features, &entries[3].features));
+    assert!(!Arc::ptr_eq(&entries[0].features, &entries[1].features));
+}
+
+#[test]
+fn edit_file() {
+    let original = "\
+{\"name\":\"foo\",\"vers\":\"0.1.0\",\"deps\":[],\"cksum\":\"0000000000000000000000000000000000000000000000000000000000000000\",\"features\":{},\"yanked\":false}
+{\"name\":\"foo\", \"vers\":\"0.2.0+old\", \"deps\":[], \"cksum\":\"1111111111111111111111111111111111111111111111111111111111111111\", \"features\":{}}";
+    let mut file = index::IndexFile::parse(original).unwrap();
+    assert_eq!(file.to_string(), original);
+
+    let mut new: Entry = serde_json::from_str(FILE.lines().next().unwrap()).unwrap();
+    new.version = "0.2.0+new".parse().unwrap();
+    assert!(matches!(
+        file.append(&new),
+        Err(index::EditError::AlreadyExists(v)) if v.to_string() == "0.2.0+old"
+    ));
+
+    new.version = "0.3.0".parse().unwrap();
+    file.append(&new).unwrap();
+    let written = file.to_string();
+    let (old, appended) = written.split_at(original.len() + 1);
+    assert_eq!(old, format!("{original}\n"));
+    assert!(appended.contains("\"vers\":\"0.3.0\""));
+    assert!(appended.ends_with('\n'));
+
+    // Yanking only touches the one line.
+    file.set_yanked(&"0.1.0".parse().unwrap(), true).unwrap();
+    let lines: Vec<_> = file.to_string().lines().map(String::from).collect();
+    assert!(lines[0].contains("\"yanked\":true"));
+    assert_eq!(lines[1], original.lines().nth(1).unwrap());
+    let entries: Vec<Entry> = index::read_entries(&file.to_string())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(entries[0].yanked);
+    assert!(!entries[2].yanked);
+
+    assert!(matches!(
+        file.set_yanked(&"0.4.0".parse().unwrap(), true),
+        Err(index::EditError::NotFound(_))
+    ));
+
+    // Build metadata is ignored when looking up versions.
+    file.set_yanked(&"0.2.0".parse().unwrap(), true).unwrap();
+    let entries: Vec<Entry> = index::read_entries(&file.to_string())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(entries[1].yanked);
+
+    // Fields that `Entry` does not know about survive yanking, in their original order.
+    let line = r#"{"name":"foo","vers":"0.1.0","deps":[{"name":"bar","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal","artifact":["bin"],"lib":true}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{},"yanked":false,"rust_version":"1.60","pubtime":"2023-01-01T00:00:00Z"}"#;
+    let mut file = index::IndexFile::parse(line).unwrap();
+    file.set_yanked(&"0.1.0".parse().unwrap(), true).unwrap();
+    assert_eq!(
+        file.to_string(),
+        line.replace(r#""yanked":false"#, r#""yanked":true"#)
+    );
+    file.set_yanked(&"0.1.0".parse().unwrap(), false).unwrap();
+    assert_eq!(file.to_string(), line);
+}
+
+#[test]
+fn paths() {
+    for (name, path) in [
+        ("a", "1/a"),
+        ("ab", "2/ab"),
+        ("abc", "3/a/abc"),
+        ("abcd", "ab/cd/abcd"),
+        ("Serde_JSON", "se/rd/serde_json"),
+    ] {
+        assert_eq!(index::path_for(name).unwrap(), path);
+        assert_eq!(index::name_from_path(path), Some(&*name.to_lowercase()));
+    }
+    assert_eq!(index::prefix("Serde"), "Se/rd");
+    assert_eq!(index::prefix(""), "");
+    assert_eq!(index::prefix("éa"), "2");
+    assert_eq!(index::prefix("éab"), "3/é");
+    assert_eq!(index::prefix("aébcd"), "aé/bc");
+    assert_eq!(
+        index::sparse_url("sparse+https://index.crates.io/", "serde").unwrap(),
+        "https://index.crates.io/se/rd/serde"
+    );
+
+    assert_eq!(index::path_for(""), Err(index::NameError::Empty));
+    assert_eq!(
+        index::path_for("1abc"),
+        Err(index::NameError::InvalidStart('1'))
+    );
+    assert_eq!(
+        index::path_for("ab/cd"),
+        Err(index::NameError::InvalidChar('/'))
+    );
+    assert_eq!(
+        index::path_for(&"a".repeat(65)),
+        Err(index::NameError::TooLong)
+    );
+
+    assert_eq!(index::name_from_path("config.json"), None);
+    assert_eq!(index::name_from_path("ab/ce/abcd"), None);
+    assert_eq!(index::name_from_path("se/rd/Serde"), None);
+}
+
+#[test]
+fn config() {
+    let config: index::Config = serde_json::from_str(
+        r#"{"dl":"https://crates.io/api/v1/crates","api":"https://crates.io"}"#,
+    )
+    .unwrap();
+    assert!(!config.auth_required);
+
+    let mut entry: Entry = serde_json::from_str(FILE.lines().nth(1).unwrap()).unwrap();
+    entry.name = "Serde".into();
+    assert_eq!(
+        config.download_url(&entry),
+        "https://crates.io/api/v1/crates/Serde/0.2.0/download"
+    );
+
+    let config: index::Config = serde_json::from_str(
+        r#"{"dl":"https://dl.example.com/{prefix}/{lowerprefix}/{crate}/{version}/{sha256-checksum}","auth-required":true}"#,
+    )
+    .unwrap();
+    assert!(config.auth_required);
+    assert_eq!(config.api, None);
+    assert_eq!(
+        config.download_url(&entry),
+        format!(
+            "https://dl.example.com/Se/rd/se/rd/Serde/0.2.0/{}",
+            "1".repeat(64)
+        )
+    );
+
+    // Invalid names, as might come from a malformed index, don't panic.
+    for name in ["", "éa", "ÉÉÉÉÉ"] {
+        entry.name = name.into();
+        assert!(config.download_url(&entry).contains(name));
+    }
+}
+
+#[test]
+fn rust_version() {
+    let m: cargo_index_transit::dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            rust-version = "1.70"
+            "#,
+        )
+        .unwrap();
+    let entry =
+        index::Entry::from_manifest(m, "https://github.com/rust-lang/crates.io-index", [0; 32]);
+    assert_eq!(entry.rust_version.as_deref(), Some("1.70"));
+
+    let json = serde_json::to_string(&entry).unwrap();
+    assert!(json.contains(r#""rust_version":"1.70""#));
+    let entry: Entry = serde_json::from_str(&json).unwrap();
+    assert_eq!(entry.rust_version.as_deref(), Some("1.70"));
+
+    let entry: Entry = serde_json::from_str(FILE.lines().next().unwrap()).unwrap();
+    assert_eq!(entry.rust_version, None);
+    assert!(!serde_json::to_string(&entry)
+        .unwrap()
+        .contains("rust_version"));
+}
+
+#[test]
+fn public() {
+    let m: cargo_index_transit::dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies.bar]
+            version = "1"
+            public = true
+
+            [dependencies.baz]
+            version = "1"
+            "#,
+        )
+        .unwrap();
+    let entry =
+        index::Entry::from_manifest(m, "https://github.com/rust-lang/crates.io-index", [0; 32]);
+    assert_eq!(entry.dependencies[0].public, Some(true));
+    assert_eq!(entry.dependencies[1].public, None);
+    assert_eq!(entry.schema_version, None);
+
+    let json = serde_json::to_string(&entry).unwrap();
+    assert_eq!(json.matches(r#""public":true"#).count(), 1);
+    assert!(!json.contains(r#""public":null"#));
+}
+
+#[test]
+fn target_dependencies() {
+    let m: cargo_index_transit::dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies.bar]
+            version = "1"
+
+            [target."cfg(unix)".dependencies.libc]
+            version = "0.2"
+
+            [target.x86_64-pc-windows-msvc.dev-dependencies.winapi]
+            version = "0.3"
+
+            [target."cfg(unix)".build-dependencies.cc]
+            version = "1"
+            "#,
+        )
+        .unwrap();
+    let entry =
+        index::Entry::from_manifest(m, "https://github.com/rust-lang/crates.io-index", [0; 32]);
+    let deps: Vec<_> = entry
+        .dependencies
+        .iter()
+        .map(|d| (&*d.name, d.kind.unwrap(), d.target.as_deref().map(|t| &**t)))
+        .collect();
+    assert_eq!(
+        deps,
+        [
+            ("bar", DependencyKind::Normal, None),
+            ("libc", DependencyKind::Normal, Some("cfg(unix)")),
+            ("cc", DependencyKind::Build, Some("cfg(unix)")),
+            (
+                "winapi",
+                DependencyKind::Dev,
+                Some("x86_64-pc-windows-msvc")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn registry_url() {
+    let crates_io = index::RegistryUrl::crates_io();
+    for url in [
+        index::CRATES_IO_INDEX,
+        index::CRATES_IO_SPARSE_INDEX,
+        "https://index.crates.io",
+        "https://github.com/rust-lang/crates.io-index.git",
+        "https://github.com/rust-lang/crates.io-index/",
+        "HTTPS://GitHub.com/Rust-Lang/Crates.io-Index",
+    ] {
+        let url = index::RegistryUrl::new(url);
+        assert_eq!(url, crates_io, "{url}");
+        assert!(url.is_crates_io());
+    }
+    assert!(index::RegistryUrl::new(index::CRATES_IO_SPARSE_INDEX).is_sparse());
+
+    let a = index::RegistryUrl::new("sparse+https://Example.com/Index/");
+    assert_eq!(
+        a,
+        index::RegistryUrl::new("sparse+https://example.com/Index")
+    );
+    assert_eq!(a.canonical(), "sparse+https://example.com/Index");
+    assert_eq!(a.to_string(), "sparse+https://Example.com/Index/");
+    // Only GitHub paths are case-insensitive.
+    assert_ne!(
+        a,
+        index::RegistryUrl::new("sparse+https://example.com/index")
+    );
+    // Other than for crates.io, git and sparse indexes are different registries.
+    assert_ne!(a, index::RegistryUrl::new("https://example.com/Index"));
+    assert!(!a.is_crates_io());
+
+    // Dependencies from the registry being published to do not name their registry, however the
+    // registry is spelled.
+    let m: cargo_index_transit::dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies.bar]
+            version = "1"
+
+            [dependencies.baz]
+            version = "1"
+            registry-index = "https://example.com/index"
+            "#,
+        )
+        .unwrap();
+    let entry = index::Entry::from_manifest(m.clone(), "sparse+https://index.crates.io/", [0; 32]);
+    assert_eq!(entry.dependencies[0].registry, None);
+    assert_eq!(
+        entry.dependencies[1].registry.as_deref().map(|r| &**r),
+        Some("https://example.com/index")
+    );
+    let entry = index::Entry::from_manifest(m, "https://example.com/index.git", [0; 32]);
+    assert_eq!(
+        entry.dependencies[0].registry.as_deref().map(|r| &**r),
+        Some(index::CRATES_IO_INDEX)
+    );
+    assert_eq!(entry.dependencies[1].registry, None);
+}
+
+#[test]
+fn validate() {
+    let dep = |name: &str, optional: bool, kind: &str| {
+        util::dep(name, json!({"optional": optional, "kind": kind}))
+    };
+
+    let good = util::entry(
+        &[
+            dep("a", true, "normal"),
+            dep("b", false, "normal"),
+            dep("b", false, "dev"),
+        ],
+        json!({"default": ["c", "b/x"]}),
+        json!({"features2": {"c": ["dep:a", "a?/y"]}, "v": 2}),
+    );
+    assert_eq!(good.validate(), Ok(()));
+
+    let bad = util::entry(
+        &[
+            dep("a", true, "normal"),
+            dep("b", false, "normal"),
+            dep("b", false, "normal"),
+            dep("c", true, "normal"),
+        ],
+        json!({
+            "x": ["nope", "y"],
+            "y": ["z"],
+            "z": ["x"],
+            "w": ["w"],
+            "v": ["missing/f", "a"],
+            "u": ["dep:b"],
+            "r": ["b"],
+        }),
+        json!({"features2": {"t": ["b?/f", "dep:c"], "s": ["c"]}}),
+    );
+    assert_eq!(
+        bad.validate().unwrap_err(),
+        [
+            index::ValidationError::DuplicateDependency {
+                name: "b".into(),
+                kind: DependencyKind::Normal,
+                target: None,
+            },
+            index::ValidationError::NotOptional {
+                feature: "r".into(),
+                value: "b".into(),
+            },
+            index::ValidationError::UnknownFeature {
+                feature: "s".into(),
+                missing: "c".into(),
+            },
+            index::ValidationError::NotOptional {
+                feature: "t".into(),
+                value: "b?/f".into(),
+            },
+            index::ValidationError::NotOptional {
+                feature: "u".into(),
+                value: "dep:b".into(),
+            },
+            index::ValidationError::UnknownDependency {
+                feature: "v".into(),
+                missing: "missing".into(),
+            },
+            index::ValidationError::UnknownFeature {
+                feature: "x".into(),
+                missing: "nope".into(),
+            },
+            index::ValidationError::FeatureCycle(vec!["w".into()]),
+            index::ValidationError::FeatureCycle(vec!["x".into(), "y".into(), "z".into()]),
+            index::ValidationError::SchemaVersion(None),
+            index::ValidationError::NewSyntaxInFeatures("u".into()),
+        ]
+    );
+
+    // A long chain of features is checked without recursing once per link.
+    let chain: serde_json::Map<_, _> = (0..100_000)
+        .map(|i| (format!("f{i}"), json!([format!("f{}", i + 1)])))
+        .chain([("f100000".to_string(), json!(["f0"]))])
+        .collect();
+    let errors = util::entry(&[], chain.into(), json!({}))
+        .validate()
+        .unwrap_err();
+    assert!(matches!(&errors[..], [index::ValidationError::FeatureCycle(c)] if c.len() == 100_001));
+
+    let m: cargo_index_transit::dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str(
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies.a]
+            version = "1"
+
+            [target."cfg(unix)".dependencies.a]
+            version = "1"
+            optional = true
+
+            [features]
+            std = ["dep:a", "b/std"]
+            "#,
+        )
+        .unwrap();
+    assert_eq!(
+        m.validate().unwrap_err(),
+        [index::ValidationError::UnknownDependency {
+            feature: "std".into(),
+            missing: "b".into(),
+        }]
+    );
+}
+
+#[test]
+fn compatibility() {
+    let v = |minor| semver::Version::new(1, minor, 0);
+
+    let plain = util::entry(&[], json!({}), json!({"rust_version": "1.70"}));
+    assert_eq!(plain.min_cargo_version(), Some(v(0)));
+    assert!(plain.compatibility_warnings(&v(0)).is_empty());
+
+    let public = util::entry(
+        &[util::dep("bar", json!({"public": true}))],
+        json!({}),
+        json!({}),
+    );
+    assert_eq!(public.min_cargo_version(), Some(v(0)));
+
+    let renamed = util::entry(
+        &[util::dep("bar", json!({"package": "baz"}))],
+        json!({}),
+        json!({}),
+    );
+    assert_eq!(renamed.min_cargo_version(), Some(v(31)));
+
+    let registry = util::entry(
+        &[util::dep(
+            "bar",
+            json!({"package": "baz", "registry": "https://example.com/index"}),
+        )],
+        json!({}),
+        json!({}),
+    );
+    assert_eq!(registry.min_cargo_version(), Some(v(34)));
+
+    let features2 = util::entry(
+        &[util::dep("bar", json!({"optional": true}))],
+        json!({}),
+        json!({"features2": {"x": ["dep:bar"]}, "v": 2}),
+    );
+    assert_eq!(features2.min_cargo_version(), Some(v(60)));
+    assert_eq!(
+        features2.compatibility_warnings(&v(40)),
+        [index::CompatibilityWarning::Features2Ignored]
+    );
+    assert!(features2.compatibility_warnings(&v(51)).is_empty());
+
+    let misplaced = util::entry(
+        &[util::dep("bar", json!({"optional": true}))],
+        json!({"x": ["bar?/y"]}),
+        json!({}),
+    );
+    assert_eq!(misplaced.min_cargo_version(), Some(v(60)));
+    assert_eq!(
+        misplaced.compatibility_warnings(&v(18)),
+        [index::CompatibilityWarning::NewSyntaxInFeatures("x".into())]
+    );
+    assert!(misplaced.compatibility_warnings(&v(19)).is_empty());
+
+    let future = util::entry(&[], json!({}), json!({"v": 3}));
+    assert_eq!(future.min_cargo_version(), None);
+}
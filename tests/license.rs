@@ -0,0 +1,121 @@
+use cargo_index_transit::license::{Expression, License, ParseError, Policy};
+
+fn license(id: &str) -> Expression {
+    Expression::License(License {
+        id: id.to_string(),
+        or_later: false,
+        exception: None,
+    })
+}
+
+#[test]
+fn parse() {
+    assert_eq!(Expression::parse("MIT").unwrap(), license("MIT"));
+    assert_eq!(
+        Expression::parse("MIT OR Apache-2.0").unwrap(),
+        Expression::Or(vec![license("MIT"), license("Apache-2.0")])
+    );
+    // The legacy separator, and lower-case operators.
+    assert_eq!(
+        Expression::parse("MIT/Apache-2.0").unwrap(),
+        Expression::parse("MIT or Apache-2.0").unwrap()
+    );
+    // AND binds more tightly than OR.
+    assert_eq!(
+        Expression::parse("MIT AND Zlib OR Apache-2.0").unwrap(),
+        Expression::Or(vec![
+            Expression::And(vec![license("MIT"), license("Zlib")]),
+            license("Apache-2.0"),
+        ])
+    );
+    assert_eq!(
+        Expression::parse("MIT AND (Zlib OR Apache-2.0)").unwrap(),
+        Expression::And(vec![
+            license("MIT"),
+            Expression::Or(vec![license("Zlib"), license("Apache-2.0")]),
+        ])
+    );
+    assert_eq!(
+        Expression::parse("GPL-2.0+ WITH Classpath-exception-2.0").unwrap(),
+        Expression::License(License {
+            id: "GPL-2.0".to_string(),
+            or_later: true,
+            exception: Some("Classpath-exception-2.0".to_string()),
+        })
+    );
+
+    for s in [
+        "MIT",
+        "MIT OR Apache-2.0",
+        "MIT AND (Zlib OR Apache-2.0)",
+        "(MIT OR Zlib) OR Apache-2.0",
+        "GPL-2.0+ WITH Classpath-exception-2.0 OR MIT",
+    ] {
+        let e = Expression::parse(s).unwrap();
+        assert_eq!(e.to_string(), s);
+        assert_eq!(Expression::parse(&e.to_string()).unwrap(), e);
+    }
+
+    assert_eq!(Expression::parse(""), Err(ParseError::UnexpectedEnd));
+    assert_eq!(Expression::parse("MIT OR"), Err(ParseError::UnexpectedEnd));
+    assert_eq!(Expression::parse("(MIT"), Err(ParseError::UnexpectedEnd));
+    assert_eq!(
+        Expression::parse("MIT)"),
+        Err(ParseError::UnexpectedToken(")".to_string()))
+    );
+    assert_eq!(
+        Expression::parse("MIT Apache-2.0"),
+        Err(ParseError::UnexpectedToken("Apache-2.0".to_string()))
+    );
+    assert_eq!(
+        Expression::parse("AND MIT"),
+        Err(ParseError::UnexpectedToken("AND".to_string()))
+    );
+    assert_eq!(
+        Expression::parse("MIT, Apache-2.0"),
+        Err(ParseError::InvalidIdentifier("MIT,".to_string()))
+    );
+
+    let nested = |depth| format!("{}MIT{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(Expression::parse(&nested(64)).is_ok());
+    assert_eq!(Expression::parse(&nested(65)), Err(ParseError::TooDeep));
+    assert_eq!(
+        Expression::parse(&"(".repeat(100_000)),
+        Err(ParseError::TooDeep)
+    );
+}
+
+#[test]
+fn policy() {
+    let policy = Policy {
+        allow: ["MIT", "Apache-2.0", "GPL-2.0 WITH Classpath-exception-2.0"]
+            .map(String::from)
+            .into(),
+        deny: ["GPL-3.0"].map(String::from).into(),
+        accept_unlisted: false,
+    };
+    let ok = |s: &str| Expression::parse(s).unwrap().satisfies(&policy);
+
+    assert!(ok("MIT"));
+    assert!(ok("MIT OR GPL-3.0"));
+    assert!(!ok("MIT AND GPL-3.0"));
+    assert!(!ok("Zlib"));
+    assert!(ok("Zlib OR Apache-2.0"));
+    assert!(ok("MIT AND (Zlib OR Apache-2.0)"));
+    assert!(ok("GPL-2.0 WITH Classpath-exception-2.0"));
+    assert!(!ok("GPL-2.0"));
+
+    let policy = Policy {
+        accept_unlisted: true,
+        ..policy
+    };
+    let ok = |s: &str| Expression::parse(s).unwrap().satisfies(&policy);
+    assert!(ok("Zlib"));
+    assert!(!ok("GPL-3.0"));
+    assert!(!ok("GPL-3.0 WITH Classpath-exception-2.0"));
+    assert!(ok("GPL-3.0 OR Zlib"));
+
+    let e = Expression::parse("MIT/Apache-2.0 AND Zlib").unwrap();
+    let ids: Vec<_> = e.licenses().into_iter().map(|l| &*l.id).collect();
+    assert_eq!(ids, ["MIT", "Apache-2.0", "Zlib"]);
+}
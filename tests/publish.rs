@@ -0,0 +1,346 @@
+use cargo_index_transit::{dotcrate, index, publish};
+use std::io::Read;
+
+fn crate_version() -> publish::CrateVersion<'static> {
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        description = "A crate"
+
+        [dependencies.bar]
+        version = "1"
+        "#,
+    )
+    .unwrap();
+    publish::CrateVersion::new(
+        m,
+        (None, None),
+        "https://github.com/rust-lang/crates.io-index",
+    )
+}
+
+#[test]
+fn wire() {
+    let cv = crate_version();
+    let body = publish::encode(Vec::new(), &cv, b"tarball").unwrap();
+    let json_len = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+    assert_eq!(&body[body.len() - 11..body.len() - 7], &7u32.to_le_bytes());
+
+    let (json, mut tarball) = publish::decode(&*body, publish::WireLimits::default()).unwrap();
+    assert_eq!(json.len(), json_len);
+    let decoded: publish::CrateVersion<'_> = serde_json::from_slice(&json).unwrap();
+    assert_eq!(decoded, cv);
+    assert_eq!(tarball.len(), 7);
+    assert_eq!(tarball.read(&mut []).unwrap(), 0);
+    let mut dotcrate = Vec::new();
+    tarball.read_to_end(&mut dotcrate).unwrap();
+    assert_eq!(dotcrate, b"tarball");
+    assert!(tarball.finish().unwrap().is_empty());
+
+    // Truncated in every possible place.
+    for end in 0..body.len() {
+        let r = publish::decode(&body[..end], publish::WireLimits::default());
+        if end < 4 + json_len {
+            assert!(matches!(
+                r,
+                Err(publish::WireError::Truncated(publish::Frame::Metadata))
+            ));
+        } else if end < 4 + json_len + 4 {
+            assert!(matches!(
+                r,
+                Err(publish::WireError::Truncated(publish::Frame::Tarball))
+            ));
+        } else {
+            let (_, mut tarball) = r.unwrap();
+            let err = tarball.read_to_end(&mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    let limits = publish::WireLimits {
+        max_metadata: 16,
+        max_tarball: 16,
+    };
+    assert!(matches!(
+        publish::decode(&*body, limits),
+        Err(publish::WireError::TooLarge { frame: publish::Frame::Metadata, len, limit: 16 })
+            if len as usize == json_len
+    ));
+    let body = publish::encode(Vec::new(), &cv, &[0; 17]).unwrap();
+    let limits = publish::WireLimits {
+        max_metadata: json_len as u32,
+        max_tarball: 16,
+    };
+    assert!(matches!(
+        publish::decode(&*body, limits),
+        Err(publish::WireError::TooLarge {
+            frame: publish::Frame::Tarball,
+            len: 17,
+            limit: 16
+        })
+    ));
+}
+
+#[test]
+fn api_responses() {
+    // What crates.io sends, and cargo's crates-io crate reads.
+    let json = r#"{"crate":{"name":"foo"},"warnings":{"invalid_categories":["bogus"],"invalid_badges":[],"other":["be nice"]}}"#;
+    let r: publish::PublishResponse<'_> = serde_json::from_str(json).unwrap();
+    assert_eq!(r.warnings.invalid_categories, ["bogus"]);
+    assert!(r.warnings.invalid_badges.is_empty());
+    assert_eq!(r.warnings.other, ["be nice"]);
+    let r: publish::PublishResponse<'_> = serde_json::from_str("{}").unwrap();
+    assert_eq!(r, Default::default());
+    assert_eq!(
+        serde_json::to_string(&r).unwrap(),
+        r#"{"warnings":{"invalid_categories":[],"invalid_badges":[],"other":[]}}"#
+    );
+
+    let e = publish::ErrorResponse::new("crate `foo` already exists");
+    let json = serde_json::to_string(&e).unwrap();
+    assert_eq!(
+        json,
+        r#"{"errors":[{"detail":"crate `foo` already exists"}]}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<publish::ErrorResponse<'_>>(&json).unwrap(),
+        e
+    );
+
+    let ok: publish::OkResponse = serde_json::from_str(r#"{"ok":true}"#).unwrap();
+    assert!(ok.ok);
+    assert_eq!(serde_json::to_string(&ok).unwrap(), r#"{"ok":true}"#);
+
+    let req: publish::OwnersRequest<'_> =
+        serde_json::from_str(r#"{"users":["alice","github:org:team"]}"#).unwrap();
+    assert_eq!(req.users, ["alice", "github:org:team"]);
+    let r = publish::OwnersResponse {
+        ok: true,
+        msg: "user alice has been invited".into(),
+    };
+    let json = serde_json::to_string(&r).unwrap();
+    assert_eq!(json, r#"{"ok":true,"msg":"user alice has been invited"}"#);
+    assert_eq!(
+        serde_json::from_str::<publish::OwnersResponse<'_>>(&json).unwrap(),
+        r
+    );
+}
+
+#[test]
+fn badges() {
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+
+        [badges.maintenance]
+        status = "actively-developed"
+        "#,
+    )
+    .unwrap();
+    let cv = publish::CrateVersion::new(
+        m,
+        (None, None),
+        "https://github.com/rust-lang/crates.io-index",
+    );
+    assert_eq!(cv.badges["maintenance"]["status"], "actively-developed");
+    let json = serde_json::to_string(&cv).unwrap();
+    assert!(json.contains(r#""badges":{"maintenance":{"status":"actively-developed"}}"#));
+
+    assert!(crate_version().badges.is_empty());
+    assert!(serde_json::to_string(&crate_version())
+        .unwrap()
+        .contains(r#""badges":{}"#));
+}
+
+#[test]
+fn readme() {
+    let files = [
+        ("README.txt", &b"txt"[..]),
+        ("README", b"plain"),
+        ("docs/intro.md", b"intro"),
+        ("bad.md", b"\xff"),
+    ];
+    let file = |p: &str| files.iter().find(|(f, _)| *f == p).map(|(_, c)| *c);
+    let resolve = |readme: &str| {
+        let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(&format!(
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n{readme}"
+        ))
+        .unwrap();
+        publish::resolve_readme(&m, file)
+    };
+    let found = |readme: &str| {
+        let (path, contents) = resolve(readme).unwrap();
+        (
+            path.map(|p| p.into_owned()),
+            contents.map(|c| c.into_owned()),
+        )
+    };
+
+    assert_eq!(found(""), (Some("README.txt".into()), Some("txt".into())));
+    assert_eq!(found("readme = false"), (None, None));
+    assert_eq!(
+        found("readme = \"./docs/intro.md\""),
+        (Some("docs/intro.md".into()), Some("intro".into()))
+    );
+    assert!(matches!(
+        resolve("readme = true"),
+        Err(publish::ReadmeError::Missing(p)) if p == "README.md"
+    ));
+    assert!(matches!(
+        resolve("readme = \"bad.md\""),
+        Err(publish::ReadmeError::Encoding(p, _)) if p == "bad.md"
+    ));
+
+    let m: dotcrate::NormalizedManifest<String, String> =
+        toml_edit::de::from_str("[package]\nname = \"foo\"\nversion = \"0.1.0\"").unwrap();
+    let cv = publish::CrateVersion::new(
+        m.clone(),
+        publish::resolve_readme(&m, file).unwrap(),
+        "https://github.com/rust-lang/crates.io-index",
+    );
+    assert_eq!(cv.readme_file.as_deref(), Some("README.txt"));
+    assert_eq!(cv.readme.as_deref(), Some("txt"));
+}
+
+#[test]
+fn validate() {
+    let mut policy = publish::Policy::default();
+    policy.categories.insert("parsing".into());
+    assert_eq!(crate_version().validate(&policy), Ok(()));
+
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "Proc-Macro"
+        version = "0.1.0"
+        keywords = ["a", "b", "c", "d", "e", "-f", "this-keyword-is-too-long"]
+        categories = ["parsing", "nonsense"]
+
+        [dependencies.bar]
+        version = "*"
+
+        [dependencies.baz]
+        version = "1"
+        registry-index = "https://example.com/index"
+        "#,
+    )
+    .unwrap();
+    let mut cv = publish::CrateVersion::new(
+        m,
+        (None, Some("x".repeat(11).into())),
+        "https://github.com/rust-lang/crates.io-index",
+    );
+    cv.description = Some("x".repeat(1001).into());
+    policy.max_readme_length = 10;
+    let errors = cv.validate(&policy).unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            publish::ValidationError::ReservedName("Proc-Macro".into()),
+            publish::ValidationError::TooManyKeywords(7),
+            publish::ValidationError::InvalidKeyword("-f".into()),
+            publish::ValidationError::InvalidKeyword("this-keyword-is-too-long".into()),
+            publish::ValidationError::UnknownCategory("nonsense".into()),
+            publish::ValidationError::DescriptionTooLong(1001),
+            publish::ValidationError::ReadmeTooLong(11),
+            publish::ValidationError::WildcardDependency("bar".into()),
+            publish::ValidationError::OtherRegistry {
+                dependency: "baz".into(),
+                registry: "https://example.com/index".into()
+            },
+        ]
+    );
+
+    // The sparse index at the same URL is a different registry.
+    policy
+        .allowed_registries
+        .insert("sparse+https://example.com/index".into());
+    assert!(cv
+        .validate(&policy)
+        .unwrap_err()
+        .iter()
+        .any(|e| matches!(e, publish::ValidationError::OtherRegistry { .. })));
+    policy
+        .allowed_registries
+        .insert("https://Example.com/index/".into());
+    assert!(!cv
+        .validate(&policy)
+        .unwrap_err()
+        .iter()
+        .any(|e| matches!(e, publish::ValidationError::OtherRegistry { .. })));
+
+    cv.name = "9lives".into();
+    assert_eq!(
+        cv.validate(&policy).unwrap_err()[0],
+        publish::ValidationError::InvalidName(index::NameError::InvalidStart('9'))
+    );
+}
+
+#[test]
+fn conversion_options() {
+    let m: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+
+        [dependencies.internal]
+        version = "1"
+
+        [dependencies.other]
+        version = "1"
+        registry-index = "https://other.example.com/index"
+
+        [dependencies.serde]
+        version = "1"
+        registry-index = "https://github.com/rust-lang/crates.io-index"
+        "#,
+    )
+    .unwrap();
+    let registry = |cv: &publish::CrateVersion<'_>| -> Vec<Option<String>> {
+        cv.dependencies
+            .iter()
+            .map(|d| d.registry.as_ref().map(|r| r.to_string()))
+            .collect()
+    };
+
+    // Unqualified dependencies are from the internal registry.
+    let mut options = publish::ConversionOptions::new("sparse+https://internal.example.com/");
+    options.default_registry = options.target_registry.clone();
+    let cv = publish::CrateVersion::new_with(m.clone(), (None, None), &options);
+    assert_eq!(
+        registry(&cv),
+        [
+            None,
+            Some("https://other.example.com/index".into()),
+            Some(index::CRATES_IO_INDEX.into()),
+        ]
+    );
+
+    // The internal registry mirrors crates.io, and "other" has moved.
+    options.registry_rewrites.insert(
+        index::RegistryUrl::crates_io(),
+        options.target_registry.clone(),
+    );
+    options.registry_rewrites.insert(
+        "https://other.example.com/index/".into(),
+        "sparse+https://other.example.com/".into(),
+    );
+    let cv = publish::CrateVersion::new_with(m.clone(), (None, None), &options);
+    assert_eq!(
+        registry(&cv),
+        [None, Some("sparse+https://other.example.com/".into()), None]
+    );
+
+    let entry = index::Entry::from_manifest_with(m, &options, [0; 32]);
+    assert_eq!(entry.dependencies[0].registry, None);
+    assert_eq!(
+        entry.dependencies[1].registry.as_deref().map(|r| &**r),
+        Some("sparse+https://other.example.com/")
+    );
+    assert_eq!(entry.dependencies[2].registry, None);
+}
@@ -0,0 +1,290 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use cargo_index_transit::{
+    dotcrate, index,
+    publish::{self, DependencyKind},
+};
+use proptest::prelude::*;
+use serde_json::json;
+
+mod util;
+
+fn arb_name() -> impl Strategy<Value = Cow<'static, str>> {
+    "[a-z][a-z0-9_-]{0,4}".prop_map(Cow::Owned)
+}
+
+fn arb_maybe<T>(s: impl Strategy<Value = T>) -> impl Strategy<Value = Option<Cow<'static, str>>>
+where
+    T: std::fmt::Debug + Into<Cow<'static, str>>,
+{
+    prop::option::of(s.prop_map(Into::into))
+}
+
+fn arb_dep_kind() -> impl Strategy<Value = DependencyKind> {
+    prop_oneof![
+        Just(DependencyKind::Normal),
+        Just(DependencyKind::Build),
+        Just(DependencyKind::Dev),
+    ]
+}
+
+fn arb_req() -> impl Strategy<Value = semver::VersionReq> {
+    prop_oneof![Just("*"), Just("^1"), Just("=0.2.3"), Just(">=1, <3")]
+        .prop_map(|r| r.parse().unwrap())
+}
+
+prop_compose! {
+    fn arb_dependency()(
+        name in arb_name(),
+        explicit_name_in_toml in prop::option::of(arb_name()),
+        kind in arb_dep_kind(),
+        optional in any::<bool>(),
+        default_features in any::<bool>(),
+        features in prop::collection::vec(arb_name(), 0..3),
+        requirements in arb_req(),
+        target in arb_maybe(prop_oneof![Just("cfg(unix)"), Just("x86_64-pc-windows-msvc")]),
+        registry in arb_maybe(prop_oneof![
+            Just("https://example.com/index"),
+            Just("sparse+https://index.crates.io/"),
+        ]),
+        public in prop::option::of(any::<bool>()),
+    ) -> publish::Dependency<'static> {
+        publish::Dependency {
+            optional,
+            default_features,
+            name,
+            features,
+            requirements,
+            target,
+            kind,
+            registry,
+            explicit_name_in_toml,
+            public,
+        }
+    }
+}
+
+fn arb_feature_value() -> impl Strategy<Value = Cow<'static, str>> {
+    prop_oneof![
+        arb_name(),
+        arb_name().prop_map(|d| format!("dep:{d}").into()),
+        (arb_name(), arb_name()).prop_map(|(d, f)| format!("{d}/{f}").into()),
+        (arb_name(), arb_name()).prop_map(|(d, f)| format!("{d}?/{f}").into()),
+    ]
+}
+
+prop_compose! {
+    /// A publish payload without any of the metadata the index does not hold.
+    fn arb_crate_version()(
+        name in arb_name(),
+        version in (0u64..3, 0u64..3, 0u64..3),
+        dependencies in prop::collection::vec(arb_dependency(), 0..5),
+        features in prop::collection::btree_map(
+            arb_name(),
+            prop::collection::vec(arb_feature_value(), 0..3),
+            0..4,
+        ),
+        links in arb_maybe(arb_name()),
+        rust_version in arb_maybe(prop_oneof![Just("1.60"), Just("1.70.0")]),
+    ) -> publish::CrateVersion<'static> {
+        publish::CrateVersion {
+            name,
+            version: semver::Version::new(version.0, version.1, version.2),
+            dependencies,
+            features,
+            authors: Vec::new(),
+            description: None,
+            documentation: None,
+            homepage: None,
+            readme: None,
+            readme_file: None,
+            keywords: Vec::new(),
+            categories: Vec::new(),
+            license: None,
+            license_file: None,
+            repository: None,
+            links,
+            rust_version,
+            badges: BTreeMap::new(),
+        }
+    }
+}
+
+/// Registries that dependencies come from in [`registry_rewrites_roundtrip`].
+const REGISTRIES: [&str; 4] = [
+    index::CRATES_IO_INDEX,
+    "https://a.example.com/index",
+    "sparse+https://b.example.com/",
+    "sparse+https://c.example.com/",
+];
+
+prop_compose! {
+    /// Options with rewrites that permute some of [`REGISTRIES`], so that they can be undone.
+    fn arb_options()(
+        moved in prop::sample::subsequence(REGISTRIES.to_vec(), 0..=REGISTRIES.len()),
+    )(
+        target in prop::sample::select(REGISTRIES.to_vec()),
+        to in Just(moved.clone()).prop_shuffle(),
+        moved in Just(moved),
+    ) -> publish::ConversionOptions {
+        let mut options = publish::ConversionOptions::new(target);
+        options.registry_rewrites = moved
+            .into_iter()
+            .zip(to)
+            .map(|(from, to)| (from.into(), to.into()))
+            .collect();
+        options
+    }
+}
+
+/// A manifest with a dependency (with version `1`) from each registry in `deps`.
+fn manifest(
+    deps: &BTreeMap<Cow<'static, str>, Option<&str>>,
+) -> dotcrate::NormalizedManifest<String, String> {
+    let mut toml = String::from("[package]\nname = \"foo\"\nversion = \"0.1.0\"\n");
+    for (name, registry) in deps {
+        toml.push_str(&format!("[dependencies.{name}]\nversion = \"1\"\n"));
+        if let Some(registry) = registry {
+            toml.push_str(&format!("registry-index = \"{registry}\"\n"));
+        }
+    }
+    toml_edit::de::from_str(&toml).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn publish_to_index_and_back(cv in arb_crate_version(), checksum in any::<[u8; 32]>()) {
+        let entry = index::Entry::from_publish(cv.clone(), checksum);
+        let back = publish::CrateVersion::from_entry(&entry);
+        prop_assert_eq!(&back, &cv);
+
+        let entry_again = index::Entry::from_publish(back, checksum);
+        prop_assert_eq!(entry_again, entry);
+    }
+
+    #[test]
+    fn publish_to_same_registry(cv in arb_crate_version()) {
+        // Publishing into the registry an entry came from leaves its registries alone, up to how
+        // the URLs are spelled.
+        let entry = index::Entry::from_publish(cv.clone(), [0; 32]);
+        let options = publish::ConversionOptions::new(index::CRATES_IO_INDEX);
+        let back = publish::CrateVersion::from_entry_with(&entry, &options, &options);
+        for (a, b) in back.dependencies.iter().zip(&cv.dependencies) {
+            prop_assert_eq!(
+                a.registry.as_deref().map(index::RegistryUrl::new),
+                b.registry
+                    .as_deref()
+                    .map(index::RegistryUrl::new)
+                    .filter(|r| !r.is_crates_io())
+            );
+        }
+    }
+
+    #[test]
+    fn registry_rewrites_roundtrip(
+        deps in prop::collection::btree_map(
+            arb_name(),
+            prop::option::of(prop::sample::select(REGISTRIES.to_vec())),
+            0..5,
+        ),
+        options in arb_options(),
+        elsewhere in prop::sample::select(REGISTRIES.to_vec()),
+    ) {
+        let cv = publish::CrateVersion::new_with(manifest(&deps), (None, None), &options);
+        let entry = index::Entry::from_publish(cv.clone(), [0; 32]);
+
+        // Going back to the same registry with the same options gives the same registries.
+        let back = publish::CrateVersion::from_entry_with(&entry, &options, &options);
+        prop_assert_eq!(back.dependencies.len(), cv.dependencies.len());
+        for (a, b) in back.dependencies.iter().zip(&cv.dependencies) {
+            prop_assert_eq!(
+                a.registry.as_deref().map(index::RegistryUrl::new),
+                b.registry.as_deref().map(index::RegistryUrl::new)
+            );
+        }
+
+        // Publishing elsewhere, without rewrites, undoes the rewrites.
+        let elsewhere = publish::ConversionOptions::new(elsewhere);
+        let moved = publish::CrateVersion::from_entry_with(&entry, &options, &elsewhere);
+        for (d, registry) in moved.dependencies.iter().zip(deps.values()) {
+            let source = index::RegistryUrl::new(registry.unwrap_or(index::CRATES_IO_INDEX));
+            prop_assert_eq!(
+                d.registry.as_deref().map(index::RegistryUrl::new),
+                Some(source).filter(|s| *s != elsewhere.target_registry)
+            );
+        }
+    }
+}
+
+#[test]
+fn from_entry() {
+    let entry: index::Entry<String, semver::Version, semver::VersionReq, String, String, String> =
+        serde_json::from_str(
+            r#"{"name":"foo","vers":"0.1.0","deps":[{"name":"bar","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"package":"baz"}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{"default":["bar"]},"features2":{"x":["dep:bar"]},"yanked":false,"v":2}"#,
+        )
+        .unwrap();
+    let cv = publish::CrateVersion::from_entry(&entry);
+    assert_eq!(cv.features.len(), 2);
+    assert_eq!(cv.features["x"], ["dep:bar"]);
+
+    // A feature in both tables gets the values from both.
+    let both = util::entry(
+        &[util::dep("bar", json!({"optional": true}))],
+        json!({"x": ["bar/std"]}),
+        json!({"features2": {"x": ["dep:bar"]}, "v": 2}),
+    );
+    assert_eq!(
+        publish::CrateVersion::from_entry(&both).features["x"],
+        ["bar/std", "dep:bar"]
+    );
+    let dep = &cv.dependencies[0];
+    assert_eq!(dep.name, "baz");
+    assert_eq!(dep.explicit_name_in_toml.as_deref(), Some("bar"));
+    assert_eq!(dep.kind, DependencyKind::Normal);
+
+    // Re-publishing from a registry that mirrors crates.io to crates.io itself.
+    let mut internal = publish::ConversionOptions::new("sparse+https://internal.example.com/");
+    internal.registry_rewrites.insert(
+        index::RegistryUrl::crates_io(),
+        internal.target_registry.clone(),
+    );
+    let crates_io = publish::ConversionOptions::new(index::CRATES_IO_INDEX);
+    let cv = publish::CrateVersion::from_entry_with(&entry, &internal, &crates_io);
+    assert_eq!(cv.dependencies[0].registry, None);
+    let cv = publish::CrateVersion::from_entry_with(
+        &entry,
+        &publish::ConversionOptions::new("sparse+https://internal.example.com/"),
+        &crates_io,
+    );
+    assert_eq!(
+        cv.dependencies[0].registry.as_deref(),
+        Some("sparse+https://internal.example.com/")
+    );
+
+    // Metadata comes from the .crate file.
+    let manifest: dotcrate::NormalizedManifest<String, String> = toml_edit::de::from_str(
+        r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        description = "A foo"
+        license = "MIT"
+        "#,
+    )
+    .unwrap();
+    let bytes = dotcrate::Builder::new()
+        .file("src/lib.rs", b"")
+        .file("README.md", b"# foo")
+        .build(&manifest)
+        .unwrap();
+    let dotcrate = dotcrate::DotCrate::from_bytes(&bytes).unwrap();
+    let cv = publish::CrateVersion::from_entry(&entry)
+        .with_metadata_from(&dotcrate)
+        .unwrap();
+    assert_eq!(cv.description.as_deref(), Some("A foo"));
+    assert_eq!(cv.license.as_deref(), Some("MIT"));
+    assert_eq!(cv.readme_file.as_deref(), Some("README.md"));
+    assert_eq!(cv.readme.as_deref(), Some("# foo"));
+    assert_eq!(cv.dependencies[0].name, "baz");
+}
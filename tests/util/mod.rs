@@ -1,9 +1,7 @@
 use cargo::core::Shell;
 use cargo::ops::NewProjectKind;
 use cargo_index_transit as cit;
-use flate2::read::GzDecoder;
 use std::borrow::Cow;
-use std::io::Read;
 use std::path::Path;
 
 #[allow(dead_code)]
@@ -71,49 +69,70 @@ pub fn roundtrip(
     .unwrap()
     .unwrap();
 
-    let decoder = GzDecoder::new(tarball.file());
-    let mut archive = tar::Archive::new(decoder);
+    let krate = cit::dotcrate::DotCrate::open(tarball.path()).unwrap();
+    let m: cit::dotcrate::NormalizedManifest<String, String> = krate.manifest().unwrap();
 
-    for entry in archive.entries().unwrap() {
-        let mut entry = entry.unwrap();
-        let path = entry.path().unwrap();
+    let repo = "https://github.com/rust-lang/crates.io-index";
+    let p: cit::publish::CrateVersion<'_> =
+        cit::publish::CrateVersion::new(m.clone(), (None, None), repo);
+    let json = serde_json::to_string(&p).unwrap();
+    let p2: crates_io::NewCrate = serde_json::from_str(&json).unwrap();
+    let json = serde_json::to_string(&p2).unwrap();
+    let p3: cit::publish::CrateVersion<'_> = serde_json::from_str(&json).unwrap();
+    assert_eq!(p, p3);
 
-        if path.ends_with("Cargo.toml") {
-            let mut manifest = String::new();
-            entry.read_to_string(&mut manifest).unwrap();
-
-            let m: cit::dotcrate::NormalizedManifest<String, String> =
-                toml_edit::de::from_str(&manifest).unwrap();
-
-            let repo = "https://github.com/rust-lang/crates.io-index";
-            let p: cit::publish::CrateVersion<'_> =
-                cit::publish::CrateVersion::new(m.clone(), (None, None), repo);
-            let json = serde_json::to_string(&p).unwrap();
-            let p2: crates_io::NewCrate = serde_json::from_str(&json).unwrap();
-            let json = serde_json::to_string(&p2).unwrap();
-            let p3: cit::publish::CrateVersion<'_> = serde_json::from_str(&json).unwrap();
-            assert_eq!(p, p3);
+    let i0 = cit::index::Entry::from_manifest(m.clone(), repo, [0; 32]);
+    let i = cit::index::Entry::from_publish(p.clone(), [0; 32]);
+    assert_eq!(i, i0);
+    let json = serde_json::to_string(&i).unwrap();
+    let _: cargo::sources::registry::RegistryPackage = serde_json::from_str(&json).unwrap();
+    let i2: crates_index::Version = serde_json::from_str(&json).unwrap();
+    let json = serde_json::to_string(&i2).unwrap();
+    let mut i3: cit::index::Entry<_, _, _, _, _, _> = serde_json::from_str(&json).unwrap();
+    // crates_index::Version doesn't preserve schema version
+    if i.schema_version.is_some() {
+        i3.schema_version = i.schema_version;
+    }
+    assert_eq!(i, i3);
 
-            let i0 = cit::index::Entry::from_manifest(m.clone(), repo, [0; 32]);
-            let i = cit::index::Entry::from_publish(p.clone(), [0; 32]);
-            assert_eq!(i, i0);
-            let json = serde_json::to_string(&i).unwrap();
-            let _: cargo::sources::registry::RegistryPackage = serde_json::from_str(&json).unwrap();
-            let i2: crates_index::Version = serde_json::from_str(&json).unwrap();
-            let json = serde_json::to_string(&i2).unwrap();
-            let mut i3: cit::index::Entry<_, _, _, _, _, _> = serde_json::from_str(&json).unwrap();
-            // crates_index::Version doesn't preserve schema version
-            if i.schema_version.is_some() {
-                i3.schema_version = i.schema_version;
-            }
-            assert_eq!(i, i3);
+    assert_eq!(i.name, "roundtrip");
+    assert_eq!(i.version, semver::Version::new(0, 1, 0));
 
-            assert_eq!(i.name, "roundtrip");
-            assert_eq!(i.version, semver::Version::new(0, 1, 0));
+    check(&m, &p, &i);
+}
 
-            check(&m, &p, &i);
+/// An index entry for `foo 0.1.0` with the dependencies `deps` and the features `features`, plus
+/// the fields in `rest`.
+#[allow(dead_code)]
+pub fn entry(
+    deps: &[serde_json::Value],
+    features: serde_json::Value,
+    rest: serde_json::Value,
+) -> cit::index::Entry<String, semver::Version, semver::VersionReq, String, String, String> {
+    let mut entry = serde_json::json!({
+        "name": "foo",
+        "vers": "0.1.0",
+        "deps": deps,
+        "cksum": "0".repeat(64),
+        "features": features,
+    });
+    let rest = rest.as_object().expect("`rest` is an object");
+    entry.as_object_mut().unwrap().extend(rest.clone());
+    serde_json::from_value(entry).unwrap()
+}
 
-            break;
-        }
-    }
+/// A non-optional index dependency on `name = "1"`, with the fields in `rest` added or replaced.
+#[allow(dead_code)]
+pub fn dep(name: &str, rest: serde_json::Value) -> serde_json::Value {
+    let mut dep = serde_json::json!({
+        "name": name,
+        "req": "^1",
+        "features": [],
+        "optional": false,
+        "default_features": true,
+        "target": null,
+    });
+    let rest = rest.as_object().expect("`rest` is an object");
+    dep.as_object_mut().unwrap().extend(rest.clone());
+    dep
 }